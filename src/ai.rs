@@ -1,6 +1,158 @@
-use crate::{GameState, Move};
+use std::time::{Duration, Instant};
+use crate::{GameState, Move, PieceType, Side};
+use crate::transposition::{Bound, TranspositionTable};
 use rand::prelude::*;
 
+/// A score past which a position is a forced mate. Mates are scored as `MATE` minus the number
+/// of plies to the mate, so a quicker mate scores higher.
+pub const MATE: i32 = 30_000;
+/// Scores at least this far from zero are mates rather than material.
+pub const MATE_THRESHOLD: i32 = MATE - 1000;
+const INFINITY: i32 = MATE + 1;
+/// The deepest iteration tried when nothing else limits the search.
+pub const MAX_DEPTH: u32 = 64;
+/// How many moves the remaining clock time is spread over when the time control doesn't say.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+/// The size of the transposition table a search makes for itself when it isn't given one.
+pub const DEFAULT_TABLE_MEGABYTES: usize = 16;
+/// Kept back from the clock for the time it takes to report the move.
+const SAFETY_MARGIN: Duration = Duration::from_millis(20);
+/// How far past the nominal depth quiescence search may go before it settles for the evaluation.
+const MAX_PLY: i32 = 128;
+/// A capture that can't lift the score to within this of alpha even when the captured piece
+/// comes for free is not searched in quiescence.
+const DELTA_MARGIN: i32 = 200;
+/// How often, in nodes, the search looks at the clock.
+const NODES_BETWEEN_TIME_CHECKS: u64 = 1024;
+
+/// How the computer picks its moves.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Strategy {
+	/// Any legal move, chosen uniformly at random.
+	Random,
+	/// The best move found by an alpha-beta search this many plies deep, and at least one.
+	AlphaBeta { depth: u32 },
+	/// The best move found by an iterative deepening search within the given limits.
+	Limited(SearchLimits),
+}
+
+impl Strategy {
+	pub fn choose_move(&self, game: &GameState) -> Option<Move> {
+		match self {
+			Strategy::Random => next_move(game),
+			Strategy::AlphaBeta { depth } => search(game, *depth).best_move,
+			Strategy::Limited(limits) => search_with_limits(game, limits).best_move,
+		}
+	}
+}
+
+/// When an iterative deepening search stops. Every limit that is set applies, and the search
+/// stops at whichever is reached first; with none set it runs to `MAX_DEPTH`. The first iteration
+/// always finishes, so there is a move to play however tight the limits are.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SearchLimits {
+	pub depth: Option<u32>,
+	pub nodes: Option<u64>,
+	/// Exactly this long to think about the move.
+	pub move_time: Option<Duration>,
+	/// The time left on the side to move's clock, shared out over the moves still to play.
+	pub time_left: Option<Duration>,
+	/// Time added to the clock after each move.
+	pub increment: Duration,
+	/// Moves to play before the next time control, if the time control has one.
+	pub moves_to_go: Option<u32>,
+	/// Look for a mate in this many moves, and stop once one is found.
+	pub mate: Option<u32>,
+}
+
+impl SearchLimits {
+	pub fn depth(depth: u32) -> SearchLimits {
+		SearchLimits { depth: Some(depth), ..SearchLimits::default() }
+	}
+
+	pub fn nodes(nodes: u64) -> SearchLimits {
+		SearchLimits { nodes: Some(nodes), ..SearchLimits::default() }
+	}
+
+	pub fn move_time(move_time: Duration) -> SearchLimits {
+		SearchLimits { move_time: Some(move_time), ..SearchLimits::default() }
+	}
+
+	pub fn clock(time_left: Duration, increment: Duration) -> SearchLimits {
+		SearchLimits { time_left: Some(time_left), increment, ..SearchLimits::default() }
+	}
+
+	pub fn mate(moves: u32) -> SearchLimits {
+		SearchLimits { mate: Some(moves), ..SearchLimits::default() }
+	}
+
+	/// The deepest iteration these limits allow, and never less than one. A mate in N moves takes
+	/// 2N - 1 plies.
+	fn max_depth(&self) -> u32 {
+		let mate_depth = self.mate.map_or(MAX_DEPTH, |moves| (2 * moves).saturating_sub(1));
+		self.depth.unwrap_or(MAX_DEPTH).min(mate_depth).clamp(1, MAX_DEPTH)
+	}
+}
+
+/// Decides how long to think. Between iterations it says whether another one is worth starting,
+/// and during an iteration it says when time is up and the iteration must be abandoned.
+struct TimeManager {
+	start: Instant,
+	/// No new iteration starts after this much time.
+	soft_limit: Option<Duration>,
+	/// The search stops here even in the middle of an iteration.
+	hard_limit: Option<Duration>,
+}
+
+impl TimeManager {
+	fn new(limits: &SearchLimits) -> TimeManager {
+		let (soft_limit, hard_limit) = if let Some(move_time) = limits.move_time {
+			(Some(move_time), Some(move_time))
+		} else if let Some(time_left) = limits.time_left {
+			let usable = time_left.saturating_sub(SAFETY_MARGIN);
+			let moves_to_go = limits.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+			let target = (usable / moves_to_go + limits.increment * 3 / 4).min(usable);
+			// Each iteration usually takes longer than all the earlier ones together, so one started
+			// after half the target would most likely overrun it.
+			(Some(target / 2), Some((target * 2).min(usable)))
+		} else {
+			(None, None)
+		};
+		TimeManager { start: Instant::now(), soft_limit, hard_limit }
+	}
+
+	fn should_start_iteration(&self) -> bool {
+		self.soft_limit.is_none_or(|limit| self.start.elapsed() < limit)
+	}
+
+	fn out_of_time(&self) -> bool {
+		self.hard_limit.is_some_and(|limit| self.start.elapsed() >= limit)
+	}
+}
+
+/// What a search found. `score` is in centipawns from the point of view of the side to move, and
+/// `depth` is the last iteration that was completed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+	pub best_move: Option<Move>,
+	pub score: i32,
+	pub principal_variation: Vec<Move>,
+	pub depth: u32,
+	pub nodes: u64,
+}
+
+impl SearchResult {
+	/// In how many moves (not plies) the side to move mates, or is mated if negative.
+	pub fn mate_in(&self) -> Option<i32> {
+		if self.score.abs() < MATE_THRESHOLD {
+			return None;
+		}
+		let plies = MATE - self.score.abs();
+		Some(if self.score > 0 { (plies + 1) / 2 } else { -(plies / 2) })
+	}
+}
+
+/// Picks a uniformly random legal move.
 pub fn next_move(game: &GameState) -> Option<Move> {
 	let mut rng = rand::thread_rng();
 	let legal_moves = game.get_legal_moves();
@@ -11,6 +163,251 @@ pub fn next_move(game: &GameState) -> Option<Move> {
 	}
 }
 
+/// Searches `depth` plies ahead with negamax and alpha-beta pruning. A depth of 0 is taken as 1,
+/// so there is a best move whenever there is a legal one.
+pub fn search(game: &GameState, depth: u32) -> SearchResult {
+	search_with_limits(game, &SearchLimits::depth(depth.max(1)))
+}
+
+/// Searches one ply deeper at a time until `limits` says to stop, and returns the result of the
+/// last iteration that finished. Each iteration tries the previous principal variation first.
+pub fn search_with_limits(game: &GameState, limits: &SearchLimits) -> SearchResult {
+	search_with_table(game, limits, &mut TranspositionTable::new(DEFAULT_TABLE_MEGABYTES))
+}
+
+/// Like `search_with_limits`, but keeps what it learns in `table`, so that searching the next move
+/// of the same game can build on it. Clear the table between games.
+pub fn search_with_table(game: &GameState, limits: &SearchLimits, table: &mut TranspositionTable) -> SearchResult {
+	table.new_search();
+	let mut searcher = Searcher {
+		game: game.clone(),
+		table,
+		nodes: 0,
+		node_limit: limits.nodes,
+		enforce_limits: false,
+		time: TimeManager::new(limits),
+		stopped: false,
+		previous_variation: Vec::new(),
+	};
+	let mut result = SearchResult { best_move: None, score: 0, principal_variation: Vec::new(), depth: 0, nodes: 0 };
+	for depth in 1..=limits.max_depth() {
+		let (score, principal_variation) = searcher.negamax(depth, 0, -INFINITY, INFINITY, true);
+		if searcher.stopped {
+			break;
+		}
+		result = SearchResult { best_move: principal_variation.first().copied(), score, principal_variation, depth, nodes: searcher.nodes };
+		// Only the first iteration is exempt from the limits.
+		searcher.enforce_limits = true;
+		searcher.previous_variation = result.principal_variation.clone();
+
+		// Once a mate is in sight, searching deeper can't find a quicker one.
+		let nothing_deeper = result.best_move.is_none() || MATE - score.abs() <= depth as i32;
+		if nothing_deeper || !searcher.time.should_start_iteration() || limits.nodes.is_some_and(|limit| searcher.nodes >= limit) {
+			break;
+		}
+	}
+	result.nodes = searcher.nodes;
+	result
+}
+
+/// The material balance in centipawns from the point of view of the side to move.
+pub fn evaluate(game: &GameState) -> i32 {
+	let mut score = 0;
+	for piece_type in [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+		let white = game.board.pieces(Side::White, piece_type).count_ones() as i32;
+		let black = game.board.pieces(Side::Black, piece_type).count_ones() as i32;
+		score += piece_type.value() * (white - black);
+	}
+	match game.side_to_move {
+		Side::White => score,
+		Side::Black => -score,
+	}
+}
+
+struct Searcher<'a> {
+	game: GameState,
+	table: &'a mut TranspositionTable,
+	nodes: u64,
+	node_limit: Option<u64>,
+	enforce_limits: bool,
+	time: TimeManager,
+	/// Set once a limit is reached mid-iteration. Scores from then on are meaningless.
+	stopped: bool,
+	previous_variation: Vec<Move>,
+}
+
+impl Searcher<'_> {
+	/// The score of the current position for the side to move, and the line that leads to it.
+	/// `on_previous_variation` says whether every move so far followed the last iteration's
+	/// principal variation, whose next move is then searched first.
+	fn negamax(&mut self, depth: u32, ply: i32, mut alpha: i32, beta: i32, on_previous_variation: bool) -> (i32, Vec<Move>) {
+		// Quiescence counts the node itself.
+		if depth == 0 {
+			return (self.quiescence(ply, alpha, beta), Vec::new());
+		}
+		self.nodes += 1;
+		if self.should_stop() {
+			self.stopped = true;
+			return (0, Vec::new());
+		}
+		let mut moves = self.game.get_legal_moves();
+		if moves.is_empty() {
+			let score = if self.game.is_in_check(self.game.side_to_move) { -(MATE - ply) } else { 0 };
+			return (score, Vec::new());
+		}
+		if ply > 0 && self.is_draw() {
+			return (0, Vec::new());
+		}
+
+		let hash = self.game.hash();
+		let entry = self.table.probe(hash, ply);
+		if let Some(entry) = entry.filter(|entry| ply > 0 && entry.depth as u32 >= depth) {
+			let cutoff = match entry.bound {
+				Bound::Exact => true,
+				Bound::Lower => entry.score >= beta,
+				Bound::Upper => entry.score <= alpha,
+			};
+			if cutoff {
+				return (entry.score, entry.best_move.into_iter().collect());
+			}
+		}
+
+		self.order_moves(&mut moves);
+		let table_move = entry.and_then(|entry| entry.best_move);
+		let variation_move = self.previous_variation.get(ply as usize).copied().filter(|_| on_previous_variation);
+		// The previous principal variation goes ahead of the table's move, which goes ahead of the rest.
+		for first in [table_move, variation_move].into_iter().flatten() {
+			if let Some(index) = moves.iter().position(|m| *m == first) {
+				moves[..=index].rotate_right(1);
+			}
+		}
+		let original_alpha = alpha;
+		let mut best_score = -INFINITY;
+		let mut best_line = Vec::new();
+		for (i, m) in moves.into_iter().enumerate() {
+			let undo = self.game.make_move(m);
+			let (score, line) = self.negamax(depth - 1, ply + 1, -beta, -alpha, on_previous_variation && i == 0);
+			self.game.unmake_move(m, undo);
+			if self.stopped {
+				return (0, Vec::new());
+			}
+			let score = -score;
+			if score > best_score {
+				best_score = score;
+				best_line = vec![m];
+				best_line.extend(line);
+			}
+			alpha = alpha.max(score);
+			if alpha >= beta {
+				break;
+			}
+		}
+		let bound = if best_score >= beta {
+			Bound::Lower
+		} else if best_score <= original_alpha {
+			Bound::Upper
+		} else {
+			Bound::Exact
+		};
+		// Failing low means every move was refuted, so none of them is known to be best.
+		let best_move = best_line.first().copied().filter(|_| bound != Bound::Upper);
+		self.table.store(hash, depth, best_score, bound, best_move, ply);
+		(best_score, best_line)
+	}
+
+	/// Searches captures and promotions until the position is quiet, so that the evaluation is never
+	/// taken halfway through an exchange. The side to move may stand pat on the evaluation instead of
+	/// capturing, except in check, where every evasion is searched. Captures that lose material by
+	/// static exchange evaluation, or that couldn't raise the score to alpha, are skipped.
+	fn quiescence(&mut self, ply: i32, mut alpha: i32, beta: i32) -> i32 {
+		self.nodes += 1;
+		if self.should_stop() {
+			self.stopped = true;
+			return 0;
+		}
+		let mut moves = self.game.get_legal_moves();
+		let in_check = self.game.is_in_check(self.game.side_to_move);
+		if moves.is_empty() {
+			return if in_check { -(MATE - ply) } else { 0 };
+		}
+		if ply > 0 && self.is_draw() {
+			return 0;
+		}
+		let stand_pat = evaluate(&self.game);
+		if ply >= MAX_PLY {
+			return stand_pat;
+		}
+		let mut best_score = -INFINITY;
+		if !in_check {
+			if stand_pat >= beta {
+				return stand_pat;
+			}
+			best_score = stand_pat;
+			alpha = alpha.max(stand_pat);
+			moves.retain(|m| self.is_capture(*m) || m.promo.is_some());
+		}
+
+		self.order_moves(&mut moves);
+		for m in moves {
+			if !in_check {
+				let victim = match self.game.board.piece_at(m.to) {
+					Some(piece) => piece.piece_type().value(),
+					None if self.is_capture(m) => PieceType::Pawn.value(),
+					None => 0,
+				};
+				let promotion = m.promo.map_or(0, |promo| promo.value() - PieceType::Pawn.value());
+				if stand_pat + victim + promotion + DELTA_MARGIN <= alpha || self.game.see(m) < 0 {
+					continue;
+				}
+			}
+			let undo = self.game.make_move(m);
+			let score = -self.quiescence(ply + 1, -beta, -alpha);
+			self.game.unmake_move(m, undo);
+			if self.stopped {
+				return 0;
+			}
+			best_score = best_score.max(score);
+			alpha = alpha.max(score);
+			if alpha >= beta {
+				break;
+			}
+		}
+		best_score
+	}
+
+	fn is_capture(&self, m: Move) -> bool {
+		self.game.board.piece_at(m.to).is_some() ||
+			Some(m.to) == self.game.en_passant_square && self.game.board.piece_at(m.from).is_some_and(|piece| piece.piece_type() == PieceType::Pawn)
+	}
+
+	fn should_stop(&self) -> bool {
+		self.stopped || self.enforce_limits && (
+			self.node_limit.is_some_and(|limit| self.nodes > limit) ||
+			self.nodes.is_multiple_of(NODES_BETWEEN_TIME_CHECKS) && self.time.out_of_time()
+		)
+	}
+
+	/// Draws the search can see without generating moves: repetition, the fifty-move rule and
+	/// insufficient material. A single repetition is enough, as the side that could avoid it
+	/// would have done so the first time.
+	fn is_draw(&self) -> bool {
+		self.game.repetition_count() >= 2 || self.game.halfmove_clock >= 100 || self.game.has_insufficient_material()
+	}
+
+	/// Captures first, most valuable victim and then least valuable attacker first, then promotions,
+	/// then quiet moves, and last the captures that lose material by static exchange evaluation.
+	fn order_moves(&self, moves: &mut [Move]) {
+		let board = &self.game.board;
+		moves.sort_by_cached_key(|m| {
+			let attacker = board.piece_at(m.from).map_or(0, |piece| piece.piece_type().value());
+			let victim = board.piece_at(m.to).map_or(0, |piece| piece.piece_type().value());
+			let promotion = m.promo.map_or(0, PieceType::value);
+			let losing = victim > 0 && self.game.see(*m) < 0;
+			(losing, -(victim * 10 - attacker + promotion * 10))
+		});
+	}
+}
+
 #[cfg(test)]
 mod ai_tests {
 	use super::*;
@@ -20,22 +417,154 @@ mod ai_tests {
 		for _ in 0..100 {
 			println!("new game");
 			let mut game = GameState::new();
-			for _ in 0..100 {
+			while game.outcome().is_none() {
 				for potential_move in game.get_legal_moves() {
-					print!(" {} ", potential_move.to_string());
+					print!(" {} ", potential_move);
 				}
-				println!("");
-				let m = next_move(&game);
+				println!();
+				let m = next_move(&game).expect("A game without an outcome should have a legal move.");
 				print!("{:?}", game.side_to_move);
-				match m {
-					Some(m) => {
-						game.make_move(m);
-						println!("{:?}", m.to_string());
-					},
-					None => break
-				}
+				game.make_move(m);
+				println!("{:?}", m.to_string());
 				println!("{}", game.board);
 			}
+			println!("{}", game.outcome().unwrap());
+		}
+	}
+
+	fn best_move(fen: &str, depth: u32) -> SearchResult {
+		search(&GameState::from_fen(fen).unwrap(), depth)
+	}
+
+	#[test]
+	fn test_search_finds_mate() {
+		let result = best_move("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+		assert_eq!(result.best_move.unwrap().to_uci(), "a1a8");
+		assert_eq!(result.score, MATE - 1);
+		assert_eq!(result.mate_in(), Some(1));
+
+		// The capture is searched first, but mating at once scores higher.
+		let result = best_move("6k1/5ppp/8/8/8/8/8/nR4K1 w - - 0 1", 4);
+		assert_eq!(result.best_move.unwrap().to_uci(), "b1b8");
+		assert_eq!(result.mate_in(), Some(1));
+
+		let result = best_move("k7/8/2K5/8/8/8/8/7R w - - 0 1", 4);
+		assert_eq!(result.mate_in(), Some(2));
+		assert_eq!(result.principal_variation.len(), 3);
+		let mut game = GameState::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+		for m in &result.principal_variation {
+			assert!(game.try_make_move(*m).is_ok());
 		}
+		assert_eq!(game.outcome().map(|outcome| outcome.winner), Some(Some(Side::White)));
+	}
+
+	#[test]
+	fn test_search_scores_ends_of_games() {
+		let mated = best_move("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1", 3);
+		assert_eq!((mated.best_move, mated.score), (None, -MATE));
+		assert_eq!(mated.mate_in(), Some(0));
+		let stalemate = best_move("k7/2Q5/8/8/8/8/8/7K b - - 0 1", 3);
+		assert_eq!((stalemate.best_move, stalemate.score), (None, 0));
+		assert_eq!(stalemate.mate_in(), None);
+		let mated_in_one = best_move("k7/8/1K6/8/8/8/8/7R b - - 0 1", 2);
+		assert_eq!(mated_in_one.mate_in(), Some(-1));
+	}
+
+	#[test]
+	fn test_search_wins_material() {
+		let result = best_move("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2);
+		assert_eq!(result.best_move.unwrap().to_uci(), "d2d5");
+		assert!(result.score > 0);
+		// Taking the defended pawn loses the queen.
+		let result = best_move("4k3/2p5/3p4/8/8/8/8/3QK3 w - - 0 1", 2);
+		assert_ne!(result.best_move.unwrap().to_uci(), "d1d6");
+	}
+
+	#[test]
+	fn test_search_limits() {
+		let game = GameState::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+		let result = search_with_limits(&game, &SearchLimits::depth(3));
+		assert_eq!(result.depth, 3);
+		assert_eq!(result, search(&game, 3));
+
+		// The first iteration always finishes, however small the budget.
+		let result = search_with_limits(&GameState::new(), &SearchLimits::depth(0));
+		assert_eq!(result.depth, 1);
+		assert!(result.best_move.is_some());
+		let result = search_with_limits(&game, &SearchLimits::nodes(1));
+		assert_eq!(result.depth, 1);
+		assert!(game.move_is_legal(result.best_move.unwrap()));
+		let result = search_with_limits(&game, &SearchLimits::nodes(5_000));
+		assert!(result.depth >= 2 && result.nodes <= 5_001);
+		assert_eq!(result.best_move, result.principal_variation.first().copied());
+
+		let start = Instant::now();
+		let result = search_with_limits(&game, &SearchLimits::move_time(Duration::from_millis(100)));
+		assert!(start.elapsed() < Duration::from_millis(500));
+		assert!(game.move_is_legal(result.best_move.unwrap()));
+		let start = Instant::now();
+		search_with_limits(&game, &SearchLimits { moves_to_go: Some(10), ..SearchLimits::clock(Duration::from_secs(2), Duration::ZERO) });
+		assert!(start.elapsed() < Duration::from_millis(800));
+
+		let result = search_with_limits(&GameState::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap(), &SearchLimits::mate(2));
+		assert_eq!((result.mate_in(), result.depth), (Some(2), 3));
+		let result = search_with_limits(&GameState::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap(), &SearchLimits::mate(1));
+		assert_eq!((result.mate_in(), result.depth), (None, 1));
+	}
+
+	#[test]
+	fn test_search_with_table() {
+		let game = GameState::from_fen("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3").unwrap();
+		let mut table = TranspositionTable::new(1);
+		let first = search_with_table(&game, &SearchLimits::depth(4), &mut table);
+		assert!(table.hashfull() > 0);
+		// Every line searched before is a cutoff now.
+		let second = search_with_table(&game, &SearchLimits::depth(4), &mut table);
+		assert_eq!((second.best_move, second.score), (first.best_move, first.score));
+		assert!(second.nodes < first.nodes / 2);
+
+		table.clear();
+		assert_eq!(search_with_table(&game, &SearchLimits::depth(4), &mut table).nodes, first.nodes);
+
+		let mut table = TranspositionTable::new(1);
+		let result = search_with_table(&GameState::from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap(), &SearchLimits::depth(5), &mut table);
+		assert_eq!(result.mate_in(), Some(2));
+	}
+
+	#[test]
+	fn test_time_manager() {
+		let clock = TimeManager::new(&SearchLimits::clock(Duration::from_secs(60), Duration::from_secs(1)));
+		assert_eq!(clock.soft_limit, Some((Duration::from_millis(59_980) / 30 + Duration::from_millis(750)) / 2));
+		assert!(clock.should_start_iteration() && !clock.out_of_time());
+		let last_move = TimeManager::new(&SearchLimits { moves_to_go: Some(1), ..SearchLimits::clock(Duration::from_secs(1), Duration::ZERO) });
+		assert_eq!(last_move.hard_limit, Some(Duration::from_millis(980)));
+		let fixed = TimeManager::new(&SearchLimits::move_time(Duration::ZERO));
+		assert!(!fixed.should_start_iteration() && fixed.out_of_time());
+		let unlimited = TimeManager::new(&SearchLimits::nodes(100));
+		assert!(unlimited.should_start_iteration() && !unlimited.out_of_time());
+	}
+
+	#[test]
+	fn test_quiescence() {
+		// Without quiescence a one-ply search would take the pawn and never see the recapture.
+		let result = best_move("4k3/2p5/3p4/8/8/8/8/3QK3 w - - 0 1", 1);
+		assert_ne!(result.best_move.unwrap().to_uci(), "d1d6");
+		assert!(result.score > 0);
+		// The root and one quiet quiescence node after each of the twenty replies.
+		assert_eq!(search(&GameState::new(), 1).nodes, 21);
+		// One ply ends halfway through the queen trade, which quiescence finishes.
+		let result = best_move("3qk3/8/8/8/8/8/8/3QK3 w - - 0 1", 1);
+		assert_eq!(result.score, 0);
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn test_strategies() {
+		let game = GameState::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+		assert_eq!(Strategy::AlphaBeta { depth: 2 }.choose_move(&game), Some(Move::from_uci("a1a8", &game).unwrap()));
+		assert!(game.move_is_legal(Strategy::Random.choose_move(&game).unwrap()));
+		assert_eq!(Strategy::AlphaBeta { depth: 0 }.choose_move(&game), Some(Move::from_uci("a1a8", &game).unwrap()));
+		assert_eq!(search(&GameState::new(), 0).depth, 1);
+		assert_eq!(Strategy::Limited(SearchLimits::depth(2)).choose_move(&game), Some(Move::from_uci("a1a8", &game).unwrap()));
+		assert_eq!(Strategy::Random.choose_move(&GameState::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap()), None);
+	}
+}
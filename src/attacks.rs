@@ -0,0 +1,215 @@
+use crate::bitboard::{self, Bitboard};
+use crate::chess::*;
+
+/// How many of one side's pieces attack each square. A square counts as attacked whatever stands on
+/// it, so a side's own pieces show up as defended.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AttackMap {
+	counts: [[u8; 8]; 8],
+}
+
+impl AttackMap {
+	pub fn attackers(&self, square: Square) -> u8 {
+		self.counts[square.rank as usize][square.file as usize]
+	}
+
+	pub fn is_attacked(&self, square: Square) -> bool {
+		self.attackers(square) > 0
+	}
+
+	/// Every attacked square as a bitboard.
+	pub fn attacked(&self) -> Bitboard {
+		let mut attacked = 0;
+		for rank in 0..8 {
+			for file in 0..8 {
+				if self.counts[rank as usize][file as usize] > 0 {
+					attacked |= bitboard::bit((rank, file));
+				}
+			}
+		}
+		attacked
+	}
+}
+
+impl GameState {
+	/// The squares of `side`'s pieces that attack `square`.
+	pub fn attackers_of(&self, square: Square, side: Side) -> Vec<Square> {
+		let attackers = self.board.attackers(bitboard::index(square.coordinates()), side, self.board.occupied());
+		bitboard::squares(attackers).map(Square::from).collect()
+	}
+
+	pub fn is_square_attacked(&self, square: Square, by: Side) -> bool {
+		self.board.is_attacked_by(square.coordinates(), by)
+	}
+
+	/// Every square `side` attacks, with how many times.
+	pub fn attack_map(&self, side: Side) -> AttackMap {
+		let mut counts = [[0; 8]; 8];
+		for origin in self.board.squares_of(side) {
+			for square in bitboard::squares(self.attacks_from(origin)) {
+				counts[square.0 as usize][square.1 as usize] += 1;
+			}
+		}
+		AttackMap { counts }
+	}
+
+	/// `side`'s bishops, rooks and queens that would attack `square` if a single piece in between
+	/// were taken away, such as a rook behind another rook or a bishop behind a pinned piece. Direct
+	/// attackers are not included.
+	pub fn xray_attackers_of(&self, square: Square, side: Side) -> Vec<Square> {
+		let index = bitboard::index(square.coordinates());
+		let occupied = self.board.occupied();
+		let straight_sliders = self.board.pieces(side, PieceType::Rook) | self.board.pieces(side, PieceType::Queen);
+		let diagonal_sliders = self.board.pieces(side, PieceType::Bishop) | self.board.pieces(side, PieceType::Queen);
+
+		let straight = bitboard::rook_attacks(index, occupied);
+		let diagonal = bitboard::bishop_attacks(index, occupied);
+		// Lifting the first piece on each ray lets the attack run on to the second.
+		let straight_xray = bitboard::rook_attacks(index, occupied & !(straight & occupied)) & !straight;
+		let diagonal_xray = bitboard::bishop_attacks(index, occupied & !(diagonal & occupied)) & !diagonal;
+		let attackers = straight_xray & straight_sliders | diagonal_xray & diagonal_sliders;
+		bitboard::squares(attackers).map(Square::from).collect()
+	}
+
+	/// Static exchange evaluation: the material the side making `m` wins, in centipawns, if both
+	/// sides then keep capturing on the destination square with their least valuable attacker for
+	/// as long as it pays. Pieces behind a capturing slider join in as it leaves, but pins and checks
+	/// are ignored, except that a king never captures onto a square that is still defended.
+	pub fn see(&self, m: Move) -> i32 {
+		let Some(attacker) = self.board.piece_at(m.from) else {
+			return 0;
+		};
+		let square = bitboard::index(m.to);
+		let mut occupied = self.board.occupied() & !bitboard::bit(m.from);
+		let mut gains = Vec::with_capacity(32);
+		let mut on_square = attacker.piece_type();
+		match self.board.piece_at(m.to) {
+			Some(victim) => gains.push(victim.piece_type().value()),
+			None if attacker.piece_type() == PieceType::Pawn && Some(m.to) == self.en_passant_square => {
+				occupied &= !bitboard::bit((m.from.0, m.to.1));
+				gains.push(PieceType::Pawn.value());
+			},
+			None => gains.push(0),
+		}
+		if let Some(promotion) = m.promo {
+			gains[0] += promotion.value() - PieceType::Pawn.value();
+			on_square = promotion;
+		}
+
+		let mut side = attacker.side().other();
+		loop {
+			let attackers = self.board.attackers(square, side, occupied);
+			let Some(piece_type) = PIECE_TYPES.into_iter().find(|piece_type| attackers & self.board.pieces(side, *piece_type) != 0) else {
+				break;
+			};
+			if piece_type == PieceType::King && self.board.attackers(square, side.other(), occupied) != 0 {
+				break;
+			}
+			// Each gain assumes the piece just moved is taken in turn.
+			let mut gain = on_square.value() - gains[gains.len() - 1];
+			on_square = piece_type;
+			if piece_type == PieceType::Pawn && (m.to.0 == 0 || m.to.0 == 7) {
+				gain += PieceType::Queen.value() - PieceType::Pawn.value();
+				on_square = PieceType::Queen;
+			}
+			gains.push(gain);
+			let capturer = attackers & self.board.pieces(side, piece_type);
+			occupied &= !(capturer & capturer.wrapping_neg());
+			side = side.other();
+		}
+		// Work back from the end of the sequence: either side may stop capturing instead.
+		while gains.len() > 1 {
+			let last = gains.pop().unwrap();
+			let previous = gains.last_mut().unwrap();
+			*previous = -(-*previous).max(last);
+		}
+		gains[0]
+	}
+
+	/// The squares attacked by the piece on `origin`.
+	fn attacks_from(&self, origin: (i8, i8)) -> Bitboard {
+		let index = bitboard::index(origin);
+		let occupied = self.board.occupied();
+		match self.board.piece_at(origin) {
+			None => 0,
+			Some(piece) => match piece.piece_type() {
+				PieceType::Pawn => bitboard::PAWN_ATTACKS[piece.side() as usize][index],
+				PieceType::Knight => bitboard::KNIGHT_ATTACKS[index],
+				PieceType::Bishop => bitboard::bishop_attacks(index, occupied),
+				PieceType::Rook => bitboard::rook_attacks(index, occupied),
+				PieceType::Queen => bitboard::queen_attacks(index, occupied),
+				PieceType::King => bitboard::KING_ATTACKS[index],
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn square(name: &str) -> Square {
+		name.parse().unwrap()
+	}
+
+	fn squares(names: &str) -> Vec<Square> {
+		names.split_whitespace().map(square).collect()
+	}
+
+	#[test]
+	fn test_attackers_of() {
+		let game = GameState::from_fen("4k3/8/8/3p4/2N1P3/1B6/8/3RK3 w - - 0 1").unwrap();
+		assert_eq!(game.attackers_of(square("d5"), Side::White), squares("d1 e4"));
+		assert_eq!(game.attackers_of(square("e4"), Side::Black), squares("d5"));
+		assert_eq!(game.attackers_of(square("h8"), Side::White), vec![]);
+		assert!(!game.is_square_attacked(square("d8"), Side::White));
+		assert!(game.is_square_attacked(square("d2"), Side::White));
+		assert!(game.is_square_attacked(square("c4"), Side::Black));
+		assert!(game.is_square_attacked(square("c4"), Side::White));
+	}
+
+	#[test]
+	fn test_attack_map() {
+		let game = GameState::new();
+		let white = game.attack_map(Side::White);
+		assert_eq!(white.attackers(square("f3")), 3);
+		assert_eq!(white.attackers(square("d2")), 4);
+		assert_eq!(white.attackers(square("e1")), 1);
+		assert_eq!(white.attackers(square("a1")), 0);
+		assert!(!white.is_attacked(square("e4")));
+		assert_eq!(white.attacked().count_ones(), 22);
+		assert_eq!(game.attack_map(Side::Black).attacked(), white.attacked().swap_bytes());
+	}
+
+	#[test]
+	fn test_see() {
+		let see = |fen: &str, uci: &str| {
+			let game = GameState::from_fen(fen).unwrap();
+			game.see(Move::from_uci(uci, &game).unwrap())
+		};
+		assert_eq!(see("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5"), 100);
+		assert_eq!(see("4k3/2p5/3p4/8/8/8/8/3QK3 w - - 0 1", "d1d6"), -800);
+		assert_eq!(see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6"), 100);
+		assert_eq!(see("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "e2e4"), 0);
+		// The rook on d1 backs up the one in front of it.
+		assert_eq!(see("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), 100);
+		assert_eq!(see("3rk3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"), -400);
+		// The king can't take back on a square the second rook covers.
+		assert_eq!(see("4k3/3p4/8/8/8/8/3R4/3RK3 w - - 0 1", "d2d7"), 100);
+		assert_eq!(see("4k3/3p4/8/8/8/8/8/3RK3 w - - 0 1", "d1d7"), -400);
+		assert_eq!(see("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q"), 800);
+		assert_eq!(see("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q"), -100);
+	}
+
+	#[test]
+	fn test_xray_attackers_of() {
+		let game = GameState::from_fen("3qk3/8/3r4/8/8/1B6/2N5/3QK3 b - - 0 1").unwrap();
+		assert_eq!(game.xray_attackers_of(square("d1"), Side::Black), squares("d8"));
+		assert_eq!(game.xray_attackers_of(square("d6"), Side::White), vec![]);
+		assert_eq!(game.xray_attackers_of(square("a4"), Side::White), vec![]);
+		assert_eq!(game.xray_attackers_of(square("e6"), Side::White), vec![]);
+		assert_eq!(game.xray_attackers_of(square("f7"), Side::White), vec![]);
+		assert_eq!(game.xray_attackers_of(square("b3"), Side::White), squares("d1"));
+		assert_eq!(game.xray_attackers_of(square("d8"), Side::White), squares("d1"));
+	}
+}
@@ -0,0 +1,142 @@
+/// A set of squares, one bit per square. Bit `rank * 8 + file` stands for the square at
+/// `(rank, file)`, so a1 is bit 0, h1 is bit 7 and h8 is bit 63.
+pub type Bitboard = u64;
+
+pub fn index(coordinates: (i8, i8)) -> usize {
+	debug_assert!(coordinates.0 >= 0 && coordinates.0 < 8 && coordinates.1 >= 0 && coordinates.1 < 8);
+	(coordinates.0 * 8 + coordinates.1) as usize
+}
+
+pub fn bit(coordinates: (i8, i8)) -> Bitboard {
+	1 << index(coordinates)
+}
+
+pub fn coordinates(index: u32) -> (i8, i8) {
+	((index / 8) as i8, (index % 8) as i8)
+}
+
+/// The squares in `bitboard`, from a1 to h8.
+pub fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = (i8, i8)> {
+	std::iter::from_fn(move || {
+		if bitboard == 0 {
+			return None;
+		}
+		let index = bitboard.trailing_zeros();
+		bitboard &= bitboard - 1;
+		Some(coordinates(index))
+	})
+}
+
+/// The squares reached from every square by single steps of the given `(rank, file)` offsets.
+const fn step_attacks(steps: &[(i8, i8)]) -> [Bitboard; 64] {
+	let mut table = [0; 64];
+	let mut square = 0;
+	while square < 64 {
+		let mut i = 0;
+		while i < steps.len() {
+			let rank = square as i8 / 8 + steps[i].0;
+			let file = square as i8 % 8 + steps[i].1;
+			if rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+				table[square] |= 1 << (rank * 8 + file);
+			}
+			i += 1;
+		}
+		square += 1;
+	}
+	table
+}
+
+pub const KNIGHT_ATTACKS: [Bitboard; 64] = step_attacks(&[(2, 1), (2, -1), (1, 2), (1, -2), (-1, 2), (-1, -2), (-2, 1), (-2, -1)]);
+pub const KING_ATTACKS: [Bitboard; 64] = step_attacks(&[(1, 1), (1, -1), (-1, 1), (-1, -1), (1, 0), (-1, 0), (0, 1), (0, -1)]);
+/// The squares a pawn attacks, indexed by side (White first) and then square.
+pub const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [step_attacks(&[(1, 1), (1, -1)]), step_attacks(&[(-1, 1), (-1, -1)])];
+
+/// Sliding directions as `(rank, file)` steps. The first four increase the square index and the
+/// last four decrease it, which tells the lookup which end of a ray is nearest the origin.
+const DIRECTIONS: [(i8, i8); 8] = [(1, 0), (0, 1), (1, 1), (1, -1), (-1, 0), (0, -1), (-1, -1), (-1, 1)];
+const ROOK_DIRECTIONS: [usize; 4] = [0, 1, 4, 5];
+const BISHOP_DIRECTIONS: [usize; 4] = [2, 3, 6, 7];
+
+/// Every square from each square to the edge of the board in each direction, origin excluded.
+const RAYS: [[Bitboard; 64]; 8] = {
+	let mut rays = [[0; 64]; 8];
+	let mut direction = 0;
+	while direction < 8 {
+		let mut square = 0;
+		while square < 64 {
+			let mut rank = square as i8 / 8 + DIRECTIONS[direction].0;
+			let mut file = square as i8 % 8 + DIRECTIONS[direction].1;
+			while rank >= 0 && rank < 8 && file >= 0 && file < 8 {
+				rays[direction][square] |= 1 << (rank * 8 + file);
+				rank += DIRECTIONS[direction].0;
+				file += DIRECTIONS[direction].1;
+			}
+			square += 1;
+		}
+		direction += 1;
+	}
+	rays
+};
+
+/// The squares along one ray up to and including the first occupied square.
+fn ray_attacks(direction: usize, square: usize, occupied: Bitboard) -> Bitboard {
+	let ray = RAYS[direction][square];
+	let blockers = ray & occupied;
+	if blockers == 0 {
+		return ray;
+	}
+	let first_blocker = if direction < 4 { blockers.trailing_zeros() } else { 63 - blockers.leading_zeros() };
+	ray ^ RAYS[direction][first_blocker as usize]
+}
+
+/// The squares strictly between `from` and `to` if they share a rank, file or diagonal, and
+/// otherwise none.
+pub fn between(from: usize, to: usize) -> Bitboard {
+	match RAYS.iter().find(|rays| rays[from] & (1 << to) != 0) {
+		Some(rays) => rays[from] & !rays[to] & !(1 << to),
+		None => 0,
+	}
+}
+
+pub fn rook_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+	ROOK_DIRECTIONS.iter().fold(0, |attacks, direction| attacks | ray_attacks(*direction, square, occupied))
+}
+
+pub fn bishop_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+	BISHOP_DIRECTIONS.iter().fold(0, |attacks, direction| attacks | ray_attacks(*direction, square, occupied))
+}
+
+pub fn queen_attacks(square: usize, occupied: Bitboard) -> Bitboard {
+	rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_step_attacks() {
+		assert_eq!(KNIGHT_ATTACKS[index((0, 0))], bit((1, 2)) | bit((2, 1)));
+		assert_eq!(KNIGHT_ATTACKS[index((3, 3))].count_ones(), 8);
+		assert_eq!(KING_ATTACKS[index((0, 7))], bit((0, 6)) | bit((1, 6)) | bit((1, 7)));
+		assert_eq!(PAWN_ATTACKS[0][index((1, 4))], bit((2, 3)) | bit((2, 5)));
+		assert_eq!(PAWN_ATTACKS[1][index((6, 0))], bit((5, 1)));
+	}
+
+	#[test]
+	fn test_sliding_attacks() {
+		assert_eq!(rook_attacks(index((0, 0)), 0).count_ones(), 14);
+		assert_eq!(bishop_attacks(index((3, 3)), 0).count_ones(), 13);
+		let occupied = bit((0, 3)) | bit((5, 0)) | bit((2, 2));
+		assert_eq!(rook_attacks(index((0, 0)), occupied), bit((0, 1)) | bit((0, 2)) | bit((0, 3)) |
+			bit((1, 0)) | bit((2, 0)) | bit((3, 0)) | bit((4, 0)) | bit((5, 0)));
+		assert_eq!(bishop_attacks(index((4, 4)), occupied) & bit((1, 1)), 0);
+		assert_ne!(bishop_attacks(index((4, 4)), occupied) & bit((2, 2)), 0);
+		assert_eq!(queen_attacks(index((4, 4)), 0), rook_attacks(index((4, 4)), 0) | bishop_attacks(index((4, 4)), 0));
+		assert_eq!(between(index((0, 0)), index((0, 3))), bit((0, 1)) | bit((0, 2)));
+		assert_eq!(between(index((6, 1)), index((3, 4))), bit((5, 2)) | bit((4, 3)));
+		assert_eq!(between(index((0, 0)), index((1, 2))), 0);
+		assert_eq!(between(index((0, 0)), index((0, 1))), 0);
+		assert_eq!(squares(bit((7, 7)) | bit((0, 1))).collect::<Vec<_>>(), vec![(0, 1), (7, 7)]);
+	}
+}
@@ -1,6 +1,8 @@
 use std::fmt;
-use std::collections::HashMap;
 use std::collections::HashSet;
+use std::str::FromStr;
+use crate::bitboard::{self, Bitboard};
+use crate::zobrist;
 
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum Side {
@@ -8,14 +10,16 @@ pub enum Side {
 	Black
 }
 
-impl Side {
-	pub fn to_string(&self) -> String {
+impl fmt::Display for Side {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match self {
-			Side::White => String::from("w"),
-			Side::Black => String::from("b"),
+			Side::White => write!(f, "w"),
+			Side::Black => write!(f, "b"),
 		}
 	}
+}
 
+impl Side {
 	pub fn other(&self) -> Side {
 		match self {
 			Side::White => Side::Black,
@@ -24,7 +28,7 @@ impl Side {
 	}
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub enum PieceType {
 	Pawn,
 	Knight,
@@ -34,8 +38,22 @@ pub enum PieceType {
 	King,
 }
 
+pub const PIECE_TYPES: [PieceType; 6] = [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King];
+
 impl PieceType {
-	fn to_char(&self) -> char {
+	/// What the piece is worth in centipawns. The king can't be traded, so it counts for nothing.
+	pub fn value(self) -> i32 {
+		match self {
+			PieceType::Pawn => 100,
+			PieceType::Knight => 320,
+			PieceType::Bishop => 330,
+			PieceType::Rook => 500,
+			PieceType::Queen => 900,
+			PieceType::King => 0,
+		}
+	}
+
+	fn to_char(self) -> char {
 		match self {
 			PieceType::Pawn => 'p',
 			PieceType::Knight => 'n',
@@ -45,21 +63,53 @@ impl PieceType {
 			PieceType::King => 'k',
 		}
 	}
+
+	fn from_char(c: char) -> Option<PieceType> {
+		match c {
+			'p' => Some(PieceType::Pawn),
+			'n' => Some(PieceType::Knight),
+			'b' => Some(PieceType::Bishop),
+			'r' => Some(PieceType::Rook),
+			'q' => Some(PieceType::Queen),
+			'k' => Some(PieceType::King),
+			_ => None,
+		}
+	}
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Piece {
 	side: Side,
 	piece_type: PieceType,
 }
 
 impl Piece {
-	pub fn to_char(&self) -> char {
+	pub fn new(side: Side, piece_type: PieceType) -> Piece {
+		Piece { side, piece_type }
+	}
+
+	pub fn side(self) -> Side {
+		self.side
+	}
+
+	pub fn piece_type(self) -> PieceType {
+		self.piece_type
+	}
+
+	/// The piece's FEN letter: uppercase for White, lowercase for Black.
+	pub fn to_char(self) -> char {
 		match self.side {
-			Side::White => self.piece_type.to_char(),
-			Side::Black => self.piece_type.to_char().to_ascii_uppercase(),
+			Side::White => self.piece_type.to_char().to_ascii_uppercase(),
+			Side::Black => self.piece_type.to_char(),
 		}
 	}
+
+	/// Reads a FEN piece letter: uppercase for White, lowercase for Black.
+	pub fn from_fen_char(c: char) -> Option<Piece> {
+		let piece_type = PieceType::from_char(c.to_ascii_lowercase())?;
+		let side = if c.is_ascii_uppercase() { Side::White } else { Side::Black };
+		Some(Piece { side, piece_type })
+	}
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -86,79 +136,200 @@ impl Move {
 		}
 	}
 
-	pub fn from_str(string: &str) -> Move {
-		let mut parts = string.split('-');
-		Move {
-			from: Board::coordinates_from_name(parts.next().expect("Should have been a valid move.")),
-			to: Board::coordinates_from_name(parts.next().expect("Should have been a valid move.")),
-			promo: None,
+	/// The move in UCI long algebraic notation, e.g. "e2e4" or "e7e8q". Castling is written as
+	/// the king's two-square move.
+	pub fn to_uci(&self) -> String {
+		let mut uci = format!("{}{}", Board::name_from_coordinates(self.from), Board::name_from_coordinates(self.to));
+		if let Some(promo_type) = self.promo {
+			uci.push(promo_type.to_char());
 		}
+		uci
 	}
 
-	pub fn to_string(&self) -> String {
+	/// Reads a UCI move and resolves it against the legal moves in `game`. A promotion without a
+	/// piece letter becomes a queen promotion, and castling may also be given as the king taking
+	/// its own rook ("e1h1"), as some programs write it.
+	pub fn from_uci(uci: &str, game: &GameState) -> Result<Move, ParseMoveError> {
+		if uci.is_empty() {
+			return Err(ParseMoveError::Empty);
+		}
+		if uci.len() < 4 || !uci.is_char_boundary(2) || !uci.is_char_boundary(4) {
+			return Err(ParseMoveError::InvalidSquare(String::from(uci)));
+		}
+		let from = uci[..2].parse::<Square>()?.coordinates();
+		let to = uci[2..4].parse::<Square>()?.coordinates();
+		let promo = match &uci[4..] {
+			"" => None,
+			"n" => Some(PieceType::Knight),
+			"b" => Some(PieceType::Bishop),
+			"r" => Some(PieceType::Rook),
+			"q" => Some(PieceType::Queen),
+			letter => return Err(ParseMoveError::InvalidPromotion(String::from(letter))),
+		};
+
+		let legal_moves = game.get_legal_moves();
+		let candidate = Move { from, to, promo };
+		if legal_moves.contains(&candidate) {
+			return Ok(candidate);
+		}
+		let as_queen_promotion = Move::new_with_promo(from, to, PieceType::Queen);
+		if promo.is_none() && legal_moves.contains(&as_queen_promotion) {
+			return Ok(as_queen_promotion);
+		}
+		let king = Piece { side: game.side_to_move, piece_type: PieceType::King };
+		let rook = Piece { side: game.side_to_move, piece_type: PieceType::Rook };
+		if promo.is_none() && from.0 == to.0 && game.board.piece_at(from) == Some(king) && game.board.piece_at(to) == Some(rook) {
+			let castling = Move::new(from, (from.0, from.1 + 2 * (to.1 - from.1).signum()));
+			if legal_moves.contains(&castling) {
+				return Ok(castling);
+			}
+		}
+		Err(ParseMoveError::IllegalMove(String::from(uci)))
+	}
+}
+
+impl fmt::Display for Move {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		match self.promo {
-			Some(promo_type) => format!("{}-{}={}", Board::name_from_coordinates(self.from), Board::name_from_coordinates(self.to), promo_type.to_char()),
-			None => format!("{}-{}", Board::name_from_coordinates(self.from), Board::name_from_coordinates(self.to))
+			Some(promo_type) => write!(f, "{}-{}={}", Board::name_from_coordinates(self.from), Board::name_from_coordinates(self.to), promo_type.to_char()),
+			None => write!(f, "{}-{}", Board::name_from_coordinates(self.from), Board::name_from_coordinates(self.to))
+		}
+	}
+}
+
+/// Parses the `e2-e4` / `e7-e8=q` format written by `Move`'s `Display` implementation. The
+/// promotion letter may be in either case.
+impl FromStr for Move {
+	type Err = ParseMoveError;
+
+	fn from_str(string: &str) -> Result<Move, ParseMoveError> {
+		if string.is_empty() {
+			return Err(ParseMoveError::Empty);
 		}
+		let (from, rest) = string.split_once('-').ok_or_else(|| ParseMoveError::MissingSeparator(String::from(string)))?;
+		let (to, promo) = match rest.split_once('=') {
+			Some((to, promo)) => (to, Some(promo)),
+			None => (rest, None),
+		};
+		let from: Square = from.parse()?;
+		let to: Square = to.parse()?;
+		let promo = match promo {
+			None => None,
+			Some(letter) => {
+				let mut chars = letter.chars();
+				match (chars.next().map(|c| c.to_ascii_lowercase()).and_then(PieceType::from_char), chars.next()) {
+					(Some(piece_type @ (PieceType::Knight | PieceType::Bishop | PieceType::Rook | PieceType::Queen)), None) => Some(piece_type),
+					_ => return Err(ParseMoveError::InvalidPromotion(String::from(letter))),
+				}
+			}
+		};
+		Ok(Move { from: from.coordinates(), to: to.coordinates(), promo })
 	}
 }
 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseMoveError {
+	Empty,
+	MissingSeparator(String),
+	InvalidSquare(String),
+	InvalidPromotion(String),
+	IllegalMove(String),
+}
+
+impl fmt::Display for ParseMoveError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ParseMoveError::Empty => write!(f, "empty move"),
+			ParseMoveError::MissingSeparator(s) => write!(f, "expected a move like 'e2-e4', found '{}'", s),
+			ParseMoveError::InvalidSquare(s) => write!(f, "'{}' is not a square between a1 and h8", s),
+			ParseMoveError::InvalidPromotion(s) => write!(f, "'{}' is not a piece a pawn can promote to", s),
+			ParseMoveError::IllegalMove(s) => write!(f, "'{}' is not a legal move in this position", s),
+		}
+	}
+}
+
+impl std::error::Error for ParseMoveError {}
+
+/// A square on the board. `rank` and `file` run from 0 to 7 and match the `(rank, file)`
+/// coordinates used throughout `Board`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Square {
+	pub rank: i8,
+	pub file: i8,
+}
+
+impl Square {
+	pub fn new(rank: i8, file: i8) -> Square {
+		Square { rank, file }
+	}
+
+	pub fn coordinates(self) -> (i8, i8) {
+		(self.rank, self.file)
+	}
+}
+
+impl From<(i8, i8)> for Square {
+	fn from(coordinates: (i8, i8)) -> Square {
+		Square::new(coordinates.0, coordinates.1)
+	}
+}
+
+impl From<Square> for (i8, i8) {
+	fn from(square: Square) -> (i8, i8) {
+		square.coordinates()
+	}
+}
+
+impl FromStr for Square {
+	type Err = ParseMoveError;
+
+	fn from_str(name: &str) -> Result<Square, ParseMoveError> {
+		match name.as_bytes() {
+			[file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Ok(Square::new((rank - b'1') as i8, (file - b'a') as i8)),
+			_ => Err(ParseMoveError::InvalidSquare(String::from(name))),
+		}
+	}
+}
+
+impl fmt::Display for Square {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", Board::name_from_coordinates(self.coordinates()))
+	}
+}
+
+/// The pieces on the board, kept as one bitboard per side and piece type.
 #[derive(Clone)]
 pub struct Board {
-	squares: [[Option<Piece>; 8]; 8],
-	sides: HashMap<Side, HashSet<(i8, i8)>>,
-	white_king_location: (i8, i8),
-	black_king_location: (i8, i8),
+	pieces: [[Bitboard; 6]; 2],
+	occupancy: [Bitboard; 2],
+	hash: u64,
 }
 
 impl Board {
 	pub fn new() -> Board {
 		let mut board = Board::new_blank();
-		board.place_piece_on_square(Piece { piece_type: PieceType::Rook, side: Side::White}, "a1");
-		board.place_piece_on_square(Piece { piece_type: PieceType::Knight, side: Side::White}, "b1");
-		board.place_piece_on_square(Piece { piece_type: PieceType::Bishop, side: Side::White}, "c1");
-		board.place_piece_on_square(Piece { piece_type: PieceType::Queen, side: Side::White}, "d1");
-		board.place_piece_on_square(Piece { piece_type: PieceType::King, side: Side::White}, "e1");
-		board.place_piece_on_square(Piece { piece_type: PieceType::Bishop, side: Side::White}, "f1");
-		board.place_piece_on_square(Piece { piece_type: PieceType::Knight, side: Side::White}, "g1");
-		board.place_piece_on_square(Piece { piece_type: PieceType::Rook, side: Side::White}, "h1");
-		for file in ["a", "b", "c", "d", "e", "f", "g", "h"] {
-			board.place_piece_on_square(Piece { piece_type: PieceType::Pawn, side: Side::White}, format!("{}2", file).as_str());
-			board.place_piece_on_square(Piece { piece_type: PieceType::Pawn, side: Side::Black}, format!("{}7", file).as_str());
-		}
-		board.place_piece_on_square(Piece { piece_type: PieceType::Rook, side: Side::Black}, "a8");
-		board.place_piece_on_square(Piece { piece_type: PieceType::Knight, side: Side::Black}, "b8");
-		board.place_piece_on_square(Piece { piece_type: PieceType::Bishop, side: Side::Black}, "c8");
-		board.place_piece_on_square(Piece { piece_type: PieceType::Queen, side: Side::Black}, "d8");
-		board.place_piece_on_square(Piece { piece_type: PieceType::King, side: Side::Black}, "e8");
-		board.place_piece_on_square(Piece { piece_type: PieceType::Bishop, side: Side::Black}, "f8");
-		board.place_piece_on_square(Piece { piece_type: PieceType::Knight, side: Side::Black}, "g8");
-		board.place_piece_on_square(Piece { piece_type: PieceType::Rook, side: Side::Black}, "h8");
+		let back_rank = [PieceType::Rook, PieceType::Knight, PieceType::Bishop, PieceType::Queen, PieceType::King, PieceType::Bishop, PieceType::Knight, PieceType::Rook];
+		for (file, piece_type) in back_rank.into_iter().enumerate() {
+			let file = file as i8;
+			board.place_piece(Piece { piece_type, side: Side::White }, (0, file));
+			board.place_piece(Piece { piece_type: PieceType::Pawn, side: Side::White }, (1, file));
+			board.place_piece(Piece { piece_type: PieceType::Pawn, side: Side::Black }, (6, file));
+			board.place_piece(Piece { piece_type, side: Side::Black }, (7, file));
+		}
 		board
 	}
 
 	pub fn new_blank() -> Board {
-		let mut sides = HashMap::new();
-		sides.insert(Side::White, HashSet::new());
-		sides.insert(Side::Black, HashSet::new());
 		Board {
-			squares: [[None; 8]; 8],
-			sides: sides,
-			white_king_location: (-1, -1),
-			black_king_location: (-1, -1),
+			pieces: [[0; 6]; 2],
+			occupancy: [0; 2],
+			hash: 0,
 		}
 	}
-	
-	pub fn extract_file_from_name(square_name: &str) -> i8 {
-		(square_name.as_bytes()[0] as i8) - 97
-	}
-
-	pub fn extract_rank_from_name(square_name: &str) -> i8 {
-		(square_name.as_bytes()[1] as i8) - 48 - 1
-	}
 
-	pub fn coordinates_from_name(square_name: &str) -> (i8, i8) {
-		(Board::extract_rank_from_name(square_name), Board::extract_file_from_name(square_name))
+	/// The coordinates of a square named like "e4".
+	pub fn coordinates_from_name(square_name: &str) -> Result<(i8, i8), ParseMoveError> {
+		square_name.parse::<Square>().map(|square| square.coordinates())
 	}
 
 	pub fn name_from_coordinates(coordinates: (i8, i8)) -> String {
@@ -170,49 +341,96 @@ impl Board {
 	}
 
 	pub fn piece_at(&self, coordinates: (i8, i8)) -> Option<Piece> {
-		self.squares[coordinates.0 as usize][coordinates.1 as usize]
+		let square = bitboard::bit(coordinates);
+		let side = if self.occupancy[Side::White as usize] & square != 0 {
+			Side::White
+		} else if self.occupancy[Side::Black as usize] & square != 0 {
+			Side::Black
+		} else {
+			return None;
+		};
+		let piece_type = PIECE_TYPES.into_iter().find(|piece_type| self.pieces[side as usize][*piece_type as usize] & square != 0)?;
+		Some(Piece { side, piece_type })
 	}
 
+	/// The piece on the square named `square_name`, or `None` if it is empty or isn't a square.
 	pub fn piece_at_square_name(&self, square_name: &str) -> Option<Piece> {
-		let coordinates = Board::coordinates_from_name(square_name);
+		let coordinates = Board::coordinates_from_name(square_name).ok()?;
 		self.piece_at(coordinates)
 	}
 
+	/// Where `side`'s king stands, or (-1, -1) if it has none.
 	pub fn king_location(&self, side: Side) -> (i8, i8) {
-		match side {
-			Side::White => self.white_king_location,
-			Side::Black => self.black_king_location,
+		match self.pieces(side, PieceType::King) {
+			0 => (-1, -1),
+			kings => bitboard::coordinates(kings.trailing_zeros()),
 		}
 	}
 
+	pub fn pieces(&self, side: Side, piece_type: PieceType) -> Bitboard {
+		self.pieces[side as usize][piece_type as usize]
+	}
+
+	/// Every square holding one of `side`'s pieces.
+	pub fn occupancy(&self, side: Side) -> Bitboard {
+		self.occupancy[side as usize]
+	}
+
+	pub fn occupied(&self) -> Bitboard {
+		self.occupancy[0] | self.occupancy[1]
+	}
+
+	/// The squares of `side`'s pieces, from a1 to h8.
+	pub fn squares_of(&self, side: Side) -> impl Iterator<Item = (i8, i8)> {
+		bitboard::squares(self.occupancy(side))
+	}
+
+	/// Whether any of `side`'s pieces attacks `coordinates`, whatever stands there.
+	pub fn is_attacked_by(&self, coordinates: (i8, i8), side: Side) -> bool {
+		self.attackers(bitboard::index(coordinates), side, self.occupied()) != 0
+	}
+
+	/// `side`'s pieces that attack the square with bitboard index `square` if exactly the squares in
+	/// `occupied` were occupied. Pieces outside `occupied` are left out, so a hypothetical board can
+	/// be tried without changing this one.
+	pub fn attackers(&self, square: usize, side: Side, occupied: Bitboard) -> Bitboard {
+		let diagonal_sliders = self.pieces(side, PieceType::Bishop) | self.pieces(side, PieceType::Queen);
+		let straight_sliders = self.pieces(side, PieceType::Rook) | self.pieces(side, PieceType::Queen);
+		// A pawn of `side` attacks this square exactly when a pawn of the other side here would attack it.
+		let attackers = bitboard::PAWN_ATTACKS[side.other() as usize][square] & self.pieces(side, PieceType::Pawn) |
+			bitboard::KNIGHT_ATTACKS[square] & self.pieces(side, PieceType::Knight) |
+			bitboard::KING_ATTACKS[square] & self.pieces(side, PieceType::King) |
+			bitboard::bishop_attacks(square, occupied) & diagonal_sliders |
+			bitboard::rook_attacks(square, occupied) & straight_sliders;
+		attackers & occupied
+	}
+
+	/// Puts `piece` on `coordinates`, replacing whatever was there.
 	pub fn place_piece(&mut self, piece: Piece, coordinates: (i8, i8)) {
-		self.sides.get_mut(&piece.side).unwrap().insert(coordinates);
-		self.squares[coordinates.0 as usize][coordinates.1 as usize] = Some(piece);
-		if let PieceType::King = piece.piece_type {
-			match piece.side {
-				Side::White => {
-					self.white_king_location = coordinates;
-				},
-				Side::Black => {
-					self.black_king_location = coordinates;
-				}
-			}
-		}
+		self.remove_piece(coordinates);
+		let square = bitboard::bit(coordinates);
+		self.pieces[piece.side as usize][piece.piece_type as usize] |= square;
+		self.occupancy[piece.side as usize] |= square;
+		self.hash ^= zobrist::piece_key(piece, coordinates);
 	}
 
-	pub fn place_piece_on_square(&mut self, piece: Piece, square_name: &str) {
-		self.place_piece(piece, Board::coordinates_from_name(square_name));
+	pub fn place_piece_on_square(&mut self, piece: Piece, square_name: &str) -> Result<(), ParseMoveError> {
+		self.place_piece(piece, Board::coordinates_from_name(square_name)?);
+		Ok(())
 	}
 
 	pub fn remove_piece(&mut self, coordinates: (i8, i8)) {
-		for (_side, roster) in &mut self.sides {
-			roster.remove(&coordinates);
+		if let Some(removed) = self.piece_at(coordinates) {
+			let square = bitboard::bit(coordinates);
+			self.pieces[removed.side as usize][removed.piece_type as usize] &= !square;
+			self.occupancy[removed.side as usize] &= !square;
+			self.hash ^= zobrist::piece_key(removed, coordinates);
 		}
-		self.squares[coordinates.0 as usize][coordinates.1 as usize] = None;
 	}
 
-	pub fn remove_piece_from_square(&mut self, square_name: &str) {
-		self.remove_piece(Board::coordinates_from_name(square_name));
+	pub fn remove_piece_from_square(&mut self, square_name: &str) -> Result<(), ParseMoveError> {
+		self.remove_piece(Board::coordinates_from_name(square_name)?);
+		Ok(())
 	}
 
 	pub fn move_piece(&mut self, m: Move) {
@@ -226,31 +444,75 @@ impl Board {
 			None => self.place_piece(piece, m.to)
 		}
 		self.remove_piece(m.from);
+	}
 
-		let roster = self.sides.get_mut(&piece.side).unwrap();
-		roster.remove(&m.from);
-		roster.insert(m.to);
+	/// Builds a board from the piece placement field of a FEN string.
+	pub fn from_fen(placement: &str) -> Result<Board, FenError> {
+		let ranks: Vec<&str> = placement.split('/').collect();
+		if ranks.len() != 8 {
+			return Err(FenError::WrongRankCount(ranks.len()));
+		}
+		let mut board = Board::new_blank();
+		for (i, rank_string) in ranks.iter().enumerate() {
+			let rank = 7 - i as i8;
+			let mut length = 0;
+			let mut previous_was_digit = false;
+			for c in rank_string.chars() {
+				if let Some(empty) = c.to_digit(10).filter(|d| (1..=8).contains(d)) {
+					if previous_was_digit {
+						return Err(FenError::ConsecutiveDigits { rank: rank + 1 });
+					}
+					previous_was_digit = true;
+					length += empty as usize;
+				} else {
+					previous_was_digit = false;
+					let piece = Piece::from_fen_char(c).ok_or(FenError::UnknownPiece { rank: rank + 1, letter: c })?;
+					if length < 8 {
+						board.place_piece(piece, (rank, length as i8));
+					}
+					length += 1;
+				}
+			}
+			if length != 8 {
+				return Err(FenError::BadRankLength { rank: rank + 1, length });
+			}
+		}
+		Ok(board)
 	}
 
+	/// The piece placement field of a FEN string: rank 8 first, runs of empty squares as digits.
 	pub fn to_fen(&self) -> String {
 		let mut fen_string = String::from("");
-		for rank in self.squares.iter() {
-			for piece in rank {
-				fen_string.push(match piece {
-					Some(p) => p.to_char(),
-					None => ' ',
-				});
+		for rank in (0..8).rev() {
+			if rank < 7 {
+				fen_string.push('/');
+			}
+			let mut empty = 0;
+			for file in 0..8 {
+				match self.piece_at((rank, file)) {
+					Some(p) => {
+						if empty > 0 {
+							fen_string.push_str(&empty.to_string());
+							empty = 0;
+						}
+						fen_string.push(p.to_char());
+					},
+					None => empty += 1,
+				}
+			}
+			if empty > 0 {
+				fen_string.push_str(&empty.to_string());
 			}
-			fen_string.push('/');
 		}
 		fen_string
 	}
 
+	/// A debug rendering of the board, one line per rank with a space for each empty square.
 	pub fn to_fen_grid(&self) -> String {
 		let mut fen_string = String::from("");
-		for rank in self.squares.iter().rev() {
-			for piece in rank {
-				fen_string.push(match piece {
+		for rank in (0..8).rev() {
+			for file in 0..8 {
+				fen_string.push(match self.piece_at((rank, file)) {
 					Some(p) => p.to_char(),
 					None => ' ',
 				});
@@ -261,13 +523,19 @@ impl Board {
 	}
 }
 
+impl Default for Board {
+	fn default() -> Board {
+		Board::new()
+	}
+}
+
 impl fmt::Display for Board {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(f, "{}", self.to_fen_grid())
 	}
 }
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct CastlingAvailability {
 	white_can_castle_kingside: bool,
 	white_can_castle_queenside: bool,
@@ -294,52 +562,740 @@ impl CastlingAvailability {
 		}
 	}
 
-	pub fn to_string(&self) -> String {
+	fn from_fen(field: &str) -> Result<CastlingAvailability, FenError> {
+		let mut castling = CastlingAvailability::none();
+		if field == "-" {
+			return Ok(castling);
+		}
+		for c in field.chars() {
+			let right = match c {
+				'K' => &mut castling.white_can_castle_kingside,
+				'Q' => &mut castling.white_can_castle_queenside,
+				'k' => &mut castling.black_can_castle_kingside,
+				'q' => &mut castling.black_can_castle_queenside,
+				_ => return Err(FenError::InvalidCastling(String::from(field))),
+			};
+			if *right {
+				return Err(FenError::InvalidCastling(String::from(field)));
+			}
+			*right = true;
+		}
+		Ok(castling)
+	}
+}
+
+impl CastlingAvailability {
+	fn zobrist_key(&self) -> u64 {
+		let rights = [self.white_can_castle_kingside, self.white_can_castle_queenside, self.black_can_castle_kingside, self.black_can_castle_queenside];
+		(0..4).filter(|right| rights[*right]).fold(0, |key, right| key ^ zobrist::castling_key(right))
+	}
+}
+
+impl fmt::Display for CastlingAvailability {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		let mut string = String::from("");
 		if self.white_can_castle_kingside { string.push('K') }
 		if self.white_can_castle_queenside { string.push('Q') }
 		if self.black_can_castle_kingside { string.push('k') }
 		if self.black_can_castle_queenside { string.push('q') }
 		if string.is_empty() { string.push('-') }
-		string
+		write!(f, "{}", string)
+	}
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FenField {
+	Placement,
+	SideToMove,
+	Castling,
+	EnPassant,
+	HalfmoveClock,
+	FullmoveNumber,
+}
+
+impl fmt::Display for FenField {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let name = match self {
+			FenField::Placement => "piece placement",
+			FenField::SideToMove => "side to move",
+			FenField::Castling => "castling rights",
+			FenField::EnPassant => "en passant square",
+			FenField::HalfmoveClock => "halfmove clock",
+			FenField::FullmoveNumber => "fullmove number",
+		};
+		write!(f, "{}", name)
 	}
 }
 
+/// Why a FEN string could not be loaded. Ranks are numbered 1-8 as in the FEN itself.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FenError {
+	MissingField(FenField),
+	TooManyFields(usize),
+	WrongRankCount(usize),
+	BadRankLength { rank: i8, length: usize },
+	ConsecutiveDigits { rank: i8 },
+	UnknownPiece { rank: i8, letter: char },
+	InvalidSideToMove(String),
+	InvalidCastling(String),
+	InvalidEnPassant(String),
+	InvalidHalfmoveClock(String),
+	InvalidFullmoveNumber(String),
+	InvalidPosition(Vec<PositionIssue>),
+}
+
+impl FenError {
+	pub fn field(&self) -> FenField {
+		match self {
+			FenError::MissingField(field) => *field,
+			FenError::TooManyFields(_) => FenField::FullmoveNumber,
+			FenError::WrongRankCount(_)
+			| FenError::BadRankLength { .. }
+			| FenError::ConsecutiveDigits { .. }
+			| FenError::UnknownPiece { .. } => FenField::Placement,
+			FenError::InvalidSideToMove(_) => FenField::SideToMove,
+			FenError::InvalidCastling(_) => FenField::Castling,
+			FenError::InvalidEnPassant(_) => FenField::EnPassant,
+			FenError::InvalidHalfmoveClock(_) => FenField::HalfmoveClock,
+			FenError::InvalidFullmoveNumber(_) => FenField::FullmoveNumber,
+			FenError::InvalidPosition(issues) => issues.first().map_or(FenField::Placement, PositionIssue::field),
+		}
+	}
+}
+
+impl fmt::Display for FenError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}: ", self.field())?;
+		match self {
+			FenError::MissingField(_) => write!(f, "field is missing"),
+			FenError::TooManyFields(count) => write!(f, "expected at most 6 fields, found {}", count),
+			FenError::WrongRankCount(count) => write!(f, "expected 8 ranks, found {}", count),
+			FenError::BadRankLength { rank, length } => write!(f, "rank {} covers {} squares instead of 8", rank, length),
+			FenError::ConsecutiveDigits { rank } => write!(f, "rank {} has two empty-square counts in a row", rank),
+			FenError::UnknownPiece { rank, letter } => write!(f, "unknown piece letter '{}' on rank {}", letter, rank),
+			FenError::InvalidSideToMove(s) => write!(f, "expected 'w' or 'b', found '{}'", s),
+			FenError::InvalidCastling(s) => write!(f, "expected '-' or some of 'KQkq' without repeats, found '{}'", s),
+			FenError::InvalidEnPassant(s) => write!(f, "expected '-' or a square on rank 3 or 6, found '{}'", s),
+			FenError::InvalidHalfmoveClock(s) => write!(f, "expected a non-negative number, found '{}'", s),
+			FenError::InvalidFullmoveNumber(s) => write!(f, "expected a positive number, found '{}'", s),
+			FenError::InvalidPosition(issues) => {
+				let reasons: Vec<String> = issues.iter().map(PositionIssue::to_string).collect();
+				write!(f, "impossible position: {}", reasons.join("; "))
+			},
+		}
+	}
+}
+
+impl std::error::Error for FenError {}
+
+/// Something that makes a position impossible to reach, found by `GameState::validate`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PositionIssue {
+	MissingKing(Side),
+	TooManyKings(Side),
+	PawnOnBackRank(Square),
+	OpponentInCheck(Side),
+	CastlingWithoutKingAndRook { side: Side, kingside: bool },
+	EnPassantWithoutDoubleStep(Square),
+	ZeroFullmoveNumber,
+	/// A square given to `PositionBuilder` with a rank or file outside 0 to 7.
+	SquareOffBoard(Square),
+}
+
+impl PositionIssue {
+	/// The FEN field that has to change to fix the issue.
+	pub fn field(&self) -> FenField {
+		match self {
+			PositionIssue::MissingKing(_) | PositionIssue::TooManyKings(_) | PositionIssue::PawnOnBackRank(_) |
+				PositionIssue::SquareOffBoard(_) => FenField::Placement,
+			PositionIssue::OpponentInCheck(_) => FenField::SideToMove,
+			PositionIssue::CastlingWithoutKingAndRook { .. } => FenField::Castling,
+			PositionIssue::EnPassantWithoutDoubleStep(_) => FenField::EnPassant,
+			PositionIssue::ZeroFullmoveNumber => FenField::FullmoveNumber,
+		}
+	}
+}
+
+impl fmt::Display for PositionIssue {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let side_name = |side: &Side| match side {
+			Side::White => "White",
+			Side::Black => "Black",
+		};
+		match self {
+			PositionIssue::MissingKing(side) => write!(f, "{} has no king", side_name(side)),
+			PositionIssue::TooManyKings(side) => write!(f, "{} has more than one king", side_name(side)),
+			PositionIssue::PawnOnBackRank(square) => write!(f, "there is a pawn on {}", square),
+			PositionIssue::OpponentInCheck(side) => write!(f, "{} is in check but it is not their move", side_name(side)),
+			PositionIssue::CastlingWithoutKingAndRook { side, kingside } => write!(f, "{} may castle {} but the king or rook is not on its starting square",
+				side_name(side), if *kingside { "kingside" } else { "queenside" }),
+			PositionIssue::EnPassantWithoutDoubleStep(square) => write!(f, "en passant square {} does not follow a pawn's double step", square),
+			PositionIssue::ZeroFullmoveNumber => write!(f, "the fullmove number is 0 but counts from 1"),
+			PositionIssue::SquareOffBoard(square) => write!(f, "rank {} and file {} are off the board", square.rank, square.file),
+		}
+	}
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SanError {
+	Empty,
+	Syntax(String),
+	NoMatchingMove(String),
+	Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			SanError::Empty => write!(f, "empty move"),
+			SanError::Syntax(san) => write!(f, "'{}' is not a move in algebraic notation", san),
+			SanError::NoMatchingMove(san) => write!(f, "'{}' does not match any legal move", san),
+			SanError::Ambiguous(san) => write!(f, "'{}' matches more than one legal move", san),
+		}
+	}
+}
+
+impl std::error::Error for SanError {}
+
+/// Why `GameState::try_make_move` refused a move.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IllegalMove {
+	OffBoard,
+	NoPieceOnOrigin,
+	WrongSideToMove,
+	CaptureOwnPiece,
+	PieceCannotMoveThatWay,
+	NothingToCapture,
+	PathBlocked,
+	LeavesKingInCheck,
+	CastlingRightsLost,
+	CastlingOutOfCheck,
+	CastlingThroughCheck,
+	MissingPromotion,
+	InvalidPromotion,
+}
+
+impl fmt::Display for IllegalMove {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let reason = match self {
+			IllegalMove::OffBoard => "the move leaves the board",
+			IllegalMove::NoPieceOnOrigin => "there is no piece on the origin square",
+			IllegalMove::WrongSideToMove => "it is the other side's turn to move",
+			IllegalMove::CaptureOwnPiece => "the destination holds a piece of the same side",
+			IllegalMove::PieceCannotMoveThatWay => "the piece cannot move that way",
+			IllegalMove::NothingToCapture => "a pawn can only move diagonally when capturing",
+			IllegalMove::PathBlocked => "the path is blocked",
+			IllegalMove::LeavesKingInCheck => "the move would leave the king in check",
+			IllegalMove::CastlingRightsLost => "the king or rook has already moved",
+			IllegalMove::CastlingOutOfCheck => "the king cannot castle out of check",
+			IllegalMove::CastlingThroughCheck => "the king cannot castle through an attacked square",
+			IllegalMove::MissingPromotion => "a pawn reaching the last rank must promote",
+			IllegalMove::InvalidPromotion => "that promotion is not allowed",
+		};
+		write!(f, "{}", reason)
+	}
+}
+
+impl std::error::Error for IllegalMove {}
+
+/// The parts of a SAN move that constrain which legal move it means.
+struct SanPattern {
+	piece_type: PieceType,
+	from_file: Option<i8>,
+	from_rank: Option<i8>,
+	to: (i8, i8),
+	promo: Option<PieceType>,
+}
+
+impl SanPattern {
+	/// Reads piece moves and pawn moves. Piece letters must be uppercase here; `parse_san` retries
+	/// with the first letter uppercased to accept sloppy input.
+	fn parse(san: &str) -> Option<SanPattern> {
+		let mut chars: Vec<char> = san.chars().collect();
+		let piece_type = match chars.first() {
+			Some(c) if c.is_ascii_uppercase() => {
+				let piece_type = PieceType::from_char(c.to_ascii_lowercase())?;
+				chars.remove(0);
+				piece_type
+			},
+			_ => PieceType::Pawn,
+		};
+
+		let mut promo = None;
+		if let Some(last) = chars.last().filter(|c| c.is_ascii_alphabetic()) {
+			promo = Some(PieceType::from_char(last.to_ascii_lowercase())?);
+			chars.pop();
+			if chars.last() == Some(&'=') {
+				chars.pop();
+			}
+		}
+
+		if chars.len() < 2 {
+			return None;
+		}
+		let to: String = chars.split_off(chars.len() - 2).into_iter().collect();
+		let to = to.parse::<Square>().ok()?.coordinates();
+		if matches!(chars.last(), Some('x' | ':' | '-')) {
+			chars.pop();
+		}
+
+		let mut from_file = None;
+		let mut from_rank = None;
+		for c in chars {
+			match c {
+				'a'..='h' if from_file.is_none() && from_rank.is_none() => from_file = Some(c as i8 - 'a' as i8),
+				'1'..='8' if from_rank.is_none() => from_rank = Some(c as i8 - '1' as i8),
+				_ => return None,
+			}
+		}
+		Some(SanPattern { piece_type, from_file, from_rank, to, promo })
+	}
+
+	fn matches(&self, game: &GameState, m: Move) -> bool {
+		game.board.piece_at(m.from).map(|piece| piece.piece_type) == Some(self.piece_type) &&
+			m.to == self.to &&
+			m.promo == self.promo &&
+			self.from_file.is_none_or(|file| file == m.from.1) &&
+			self.from_rank.is_none_or(|rank| rank == m.from.0)
+	}
+}
+
+/// Everything that makes two positions the same for the repetition rules. The en passant
+/// square only counts when a capture onto it is actually legal.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct PositionKey {
+	pieces: [[Bitboard; 6]; 2],
+	side_to_move: Side,
+	castling_availability: CastlingAvailability,
+	en_passant_square: Option<(i8, i8)>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Termination {
+	Checkmate,
+	Stalemate,
+	InsufficientMaterial,
+	FiftyMoves,
+	SeventyFiveMoves,
+	ThreefoldRepetition,
+	FivefoldRepetition,
+	Resignation,
+	Timeout,
+	Agreement,
+}
+
+impl fmt::Display for Termination {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let reason = match self {
+			Termination::Checkmate => "checkmate",
+			Termination::Stalemate => "stalemate",
+			Termination::InsufficientMaterial => "insufficient material",
+			Termination::FiftyMoves => "fifty-move rule",
+			Termination::SeventyFiveMoves => "seventy-five-move rule",
+			Termination::ThreefoldRepetition => "threefold repetition",
+			Termination::FivefoldRepetition => "fivefold repetition",
+			Termination::Resignation => "resignation",
+			Termination::Timeout => "time forfeit",
+			Termination::Agreement => "agreement",
+		};
+		write!(f, "{}", reason)
+	}
+}
+
+/// How a finished game ended. `winner` is `None` for a draw.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Outcome {
+	pub winner: Option<Side>,
+	pub termination: Termination,
+}
+
+impl Outcome {
+	/// The result as written in PGN: "1-0", "0-1" or "1/2-1/2".
+	pub fn result(&self) -> &'static str {
+		match self.winner {
+			Some(Side::White) => "1-0",
+			Some(Side::Black) => "0-1",
+			None => "1/2-1/2",
+		}
+	}
+}
+
+impl fmt::Display for Outcome {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} ({})", self.result(), self.termination)
+	}
+}
+
+/// What `GameState::make_move` overwrites and `GameState::unmake_move` needs to put back. The
+/// captured piece is stored with its square, which differs from the destination for en passant.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MoveUndo {
+	pub captured: Option<(Piece, (i8, i8))>,
+	pub castling_availability: CastlingAvailability,
+	pub en_passant_square: Option<(i8, i8)>,
+	pub halfmove_clock: u32,
+	pub fullmove_number: u32,
+}
+
 #[derive(Clone)]
 pub struct GameState {
 	pub board: Board,
 	pub side_to_move: Side,
 	pub castling_availability: CastlingAvailability,
 	pub en_passant_square: Option<(i8, i8)>,
+	pub halfmove_clock: u32,
+	pub fullmove_number: u32,
+	position_history: Vec<PositionKey>,
+	undo_stack: Vec<(Move, MoveUndo)>,
+	redo_stack: Vec<Move>,
+	/// How many moves the undo stack held when the redo stack was last added to. Any other length
+	/// means a move was played since, so the redo stack is stale. Checking this lazily rather than
+	/// clearing the redo stack in `make_move` lets a `make_move` and `unmake_move` pair leave it alone.
+	redo_base: usize,
+	declared_outcome: Option<Outcome>,
+}
+
+impl Default for GameState {
+	fn default() -> GameState {
+		GameState::new()
+	}
 }
 
 impl GameState {
 	pub fn new() -> GameState {
-		GameState {
-			board: Board::new(),
-			side_to_move: Side::White,
-			castling_availability: CastlingAvailability::all(),
-			en_passant_square: None,
+		GameState::from_parts(Board::new(), Side::White, CastlingAvailability::all(), None, 0, 1).expect("the starting position is valid")
+	}
+
+	/// A game starting from the given position, with no moves played yet, provided `validate` accepts
+	/// the position.
+	fn from_parts(board: Board, side_to_move: Side, castling_availability: CastlingAvailability, en_passant_square: Option<(i8, i8)>,
+		halfmove_clock: u32, fullmove_number: u32) -> Result<GameState, Vec<PositionIssue>> {
+		let mut game = GameState {
+			board,
+			side_to_move,
+			castling_availability,
+			en_passant_square,
+			halfmove_clock,
+			fullmove_number,
+			position_history: Vec::new(),
+			undo_stack: Vec::new(),
+			redo_stack: Vec::new(),
+			redo_base: 0,
+			declared_outcome: None,
+		};
+		game.validate()?;
+		game.position_history.push(game.position_key());
+		Ok(game)
+	}
+
+	/// Loads a position from FEN. The two clock fields may be omitted, in which case
+	/// they default to 0 and 1.
+	pub fn from_fen(fen: &str) -> Result<GameState, FenError> {
+		let fields: Vec<&str> = fen.split_whitespace().collect();
+		if fields.len() > 6 {
+			return Err(FenError::TooManyFields(fields.len()));
 		}
+		let placement = *fields.first().ok_or(FenError::MissingField(FenField::Placement))?;
+		let side_to_move = *fields.get(1).ok_or(FenError::MissingField(FenField::SideToMove))?;
+		let castling = *fields.get(2).ok_or(FenError::MissingField(FenField::Castling))?;
+		let en_passant = *fields.get(3).ok_or(FenError::MissingField(FenField::EnPassant))?;
+
+		GameState::from_parts(
+			Board::from_fen(placement)?,
+			match side_to_move {
+				"w" => Side::White,
+				"b" => Side::Black,
+				_ => return Err(FenError::InvalidSideToMove(String::from(side_to_move))),
+			},
+			CastlingAvailability::from_fen(castling)?,
+			match en_passant {
+				"-" => None,
+				_ => {
+					match Board::coordinates_from_name(en_passant) {
+						Ok(coordinates) if coordinates.0 == 2 || coordinates.0 == 5 => Some(coordinates),
+						_ => return Err(FenError::InvalidEnPassant(String::from(en_passant))),
+					}
+				}
+			},
+			match fields.get(4) {
+				None => 0,
+				Some(clock) => clock.parse().map_err(|_| FenError::InvalidHalfmoveClock(String::from(*clock)))?,
+			},
+			match fields.get(5) {
+				None => 1,
+				Some(number) => match number.parse() {
+					Ok(n) if n > 0 => n,
+					_ => return Err(FenError::InvalidFullmoveNumber(String::from(*number))),
+				},
+			},
+		).map_err(FenError::InvalidPosition)
+	}
+
+	/// Checks that the position could arise in a game: one king each, no pawns on the first or last
+	/// rank, the side that just moved not left in check, castling rights only where the king and
+	/// rook are still at home, and an en passant square only behind a pawn that just double-stepped.
+	/// Every problem found is reported.
+	pub fn validate(&self) -> Result<(), Vec<PositionIssue>> {
+		let mut issues = Vec::new();
+		for side in [Side::White, Side::Black] {
+			match self.board.pieces(side, PieceType::King).count_ones() {
+				0 => issues.push(PositionIssue::MissingKing(side)),
+				1 => (),
+				_ => issues.push(PositionIssue::TooManyKings(side)),
+			}
+		}
+		for rank in [0, 7] {
+			for file in 0..8 {
+				if self.board.piece_at((rank, file)).is_some_and(|piece| piece.piece_type == PieceType::Pawn) {
+					issues.push(PositionIssue::PawnOnBackRank(Square::new(rank, file)));
+				}
+			}
+		}
+		let opponent = self.side_to_move.other();
+		if self.is_in_check(opponent) {
+			issues.push(PositionIssue::OpponentInCheck(opponent));
+		}
+
+		let rights = [
+			(Side::White, true, self.castling_availability.white_can_castle_kingside),
+			(Side::White, false, self.castling_availability.white_can_castle_queenside),
+			(Side::Black, true, self.castling_availability.black_can_castle_kingside),
+			(Side::Black, false, self.castling_availability.black_can_castle_queenside),
+		];
+		for (side, kingside, has_right) in rights {
+			let home_rank = match side {
+				Side::White => 0,
+				Side::Black => 7,
+			};
+			let king = Piece { side, piece_type: PieceType::King };
+			let rook = Piece { side, piece_type: PieceType::Rook };
+			if has_right && (self.board.piece_at((home_rank, 4)) != Some(king) || self.board.piece_at((home_rank, if kingside { 7 } else { 0 })) != Some(rook)) {
+				issues.push(PositionIssue::CastlingWithoutKingAndRook { side, kingside });
+			}
+		}
+
+		if let Some(square) = self.en_passant_square {
+			// The pawn that double-stepped belongs to the side that just moved.
+			let (expected_rank, ahead) = match self.side_to_move {
+				Side::White => (5, -1),
+				Side::Black => (2, 1),
+			};
+			let pawn = Piece { side: opponent, piece_type: PieceType::Pawn };
+			let origin = (square.0 - ahead, square.1);
+			if square.0 != expected_rank || self.board.piece_at((square.0 + ahead, square.1)) != Some(pawn) ||
+				self.board.piece_at(square).is_some() || self.board.piece_at(origin).is_some() {
+				issues.push(PositionIssue::EnPassantWithoutDoubleStep(Square::from(square)));
+			}
+		}
+		if self.fullmove_number == 0 {
+			issues.push(PositionIssue::ZeroFullmoveNumber);
+		}
+
+		if issues.is_empty() { Ok(()) } else { Err(issues) }
 	}
 
 	pub fn to_fen(&self) -> String {
-		format!("{} {} {} {}", 
+		format!("{} {} {} {} {} {}", 
 			self.board.to_fen(), 
-			self.side_to_move.to_string(),
-			self.castling_availability.to_string(),
+			self.side_to_move,
+			self.castling_availability,
 			match &self.en_passant_square {
 				None => String::from("-"),
 				Some(sq) => Board::name_from_coordinates(*sq),
-			}
+			},
+			self.halfmove_clock,
+			self.fullmove_number
 		)
 	}
 
-	pub fn make_move(&mut self, m: Move) {
+	/// Plays `m` and returns the record `unmake_move` needs to take it back. The move is also pushed
+	/// onto the undo stack, and any moves waiting to be redone are discarded unless the move is
+	/// unmade again. `m` must be legal; use `try_make_move` for moves that haven't been checked.
+	pub fn make_move(&mut self, m: Move) -> MoveUndo {
+		let undo = self.apply_move(m);
+		self.undo_stack.push((m, undo));
+		undo
+	}
+
+	/// Restores the exact state from before `m` was played. `m` and `undo` must be the most recent
+	/// move and the record `make_move` returned for it.
+	pub fn unmake_move(&mut self, m: Move, undo: MoveUndo) {
+		debug_assert_eq!(self.undo_stack.last().map(|(last, _)| *last), Some(m));
+		self.undo_stack.pop();
+		self.restore_move(m, undo);
+	}
+
+	/// Plays `m` if it is legal, and otherwise explains why not. Unlike `make_move`, this is safe to
+	/// call with moves a user typed in.
+	pub fn try_make_move(&mut self, m: Move) -> Result<(), IllegalMove> {
+		self.check_move(m)?;
+		self.make_move(m);
+		Ok(())
+	}
+
+	/// Whether `m` is legal, and if it isn't, the first rule it breaks.
+	pub fn check_move(&self, m: Move) -> Result<(), IllegalMove> {
+		if !Board::within_bounds(m.from) || !Board::within_bounds(m.to) {
+			return Err(IllegalMove::OffBoard);
+		}
+		let piece = self.board.piece_at(m.from).ok_or(IllegalMove::NoPieceOnOrigin)?;
+		if piece.side != self.side_to_move {
+			return Err(IllegalMove::WrongSideToMove);
+		}
+		if self.get_legal_moves().contains(&m) {
+			return Ok(());
+		}
+		if self.board.piece_at(m.to).is_some_and(|other| other.side == piece.side) {
+			return Err(IllegalMove::CaptureOwnPiece);
+		}
+
+		let rank_ahead = match piece.side {
+			Side::White => 1,
+			Side::Black => -1,
+		};
+		let reaches_last_rank = piece.piece_type == PieceType::Pawn && !Board::within_bounds((m.to.0 + rank_ahead, m.to.1));
+		match m.promo {
+			Some(PieceType::Pawn | PieceType::King) => return Err(IllegalMove::InvalidPromotion),
+			Some(_) if !reaches_last_rank => return Err(IllegalMove::InvalidPromotion),
+			None if reaches_last_rank => {
+				let as_queen = Move::new_with_promo(m.from, m.to, PieceType::Queen);
+				return Err(self.check_move(as_queen).err().unwrap_or(IllegalMove::MissingPromotion));
+			},
+			_ => (),
+		}
+
+		let rank_difference = m.to.0 - m.from.0;
+		let file_difference = m.to.1 - m.from.1;
+		if piece.piece_type == PieceType::King && rank_difference == 0 && file_difference.abs() == 2 {
+			return Err(self.castling_problem(m));
+		}
+		if !Self::could_move_on_empty_board(piece, m.from, m.to) {
+			return Err(IllegalMove::PieceCannotMoveThatWay);
+		}
+		if piece.piece_type == PieceType::Pawn {
+			let target_empty = self.board.piece_at(m.to).is_none();
+			if file_difference != 0 && target_empty && self.en_passant_square != Some(m.to) {
+				return Err(IllegalMove::NothingToCapture);
+			}
+			if file_difference == 0 && !target_empty {
+				return Err(IllegalMove::PathBlocked);
+			}
+		}
+		if matches!(piece.piece_type, PieceType::Pawn | PieceType::Bishop | PieceType::Rook | PieceType::Queen) {
+			let step = (rank_difference.signum(), file_difference.signum());
+			let mut square = (m.from.0 + step.0, m.from.1 + step.1);
+			while square != m.to {
+				if self.board.piece_at(square).is_some() {
+					return Err(IllegalMove::PathBlocked);
+				}
+				square = (square.0 + step.0, square.1 + step.1);
+			}
+		}
+		Err(IllegalMove::LeavesKingInCheck)
+	}
+
+	/// Whether `piece` moves from `from` to `to` in one go with nothing else on the board, counting
+	/// pawn captures and double steps. Castling is handled separately.
+	fn could_move_on_empty_board(piece: Piece, from: (i8, i8), to: (i8, i8)) -> bool {
+		let rank_difference = to.0 - from.0;
+		let file_difference = to.1 - from.1;
+		let straight = (rank_difference == 0) != (file_difference == 0);
+		let diagonal = rank_difference != 0 && rank_difference.abs() == file_difference.abs();
+		match piece.piece_type {
+			PieceType::Pawn => {
+				let (ahead, initial_rank) = match piece.side {
+					Side::White => (1, 1),
+					Side::Black => (-1, 6),
+				};
+				(rank_difference == ahead && file_difference.abs() <= 1) ||
+					(rank_difference == 2 * ahead && file_difference == 0 && from.0 == initial_rank)
+			},
+			PieceType::Knight => (rank_difference.abs(), file_difference.abs()) == (1, 2) || (rank_difference.abs(), file_difference.abs()) == (2, 1),
+			PieceType::Bishop => diagonal,
+			PieceType::Rook => straight,
+			PieceType::Queen => straight || diagonal,
+			PieceType::King => rank_difference.abs() <= 1 && file_difference.abs() <= 1,
+		}
+	}
+
+	/// Why the king's two-square move `m` isn't a legal castling move.
+	fn castling_problem(&self, m: Move) -> IllegalMove {
+		let kingside = m.to.1 > m.from.1;
+		let (home_rank, has_right) = match (self.side_to_move, kingside) {
+			(Side::White, true) => (0, self.castling_availability.white_can_castle_kingside),
+			(Side::White, false) => (0, self.castling_availability.white_can_castle_queenside),
+			(Side::Black, true) => (7, self.castling_availability.black_can_castle_kingside),
+			(Side::Black, false) => (7, self.castling_availability.black_can_castle_queenside),
+		};
+		if m.from != (home_rank, 4) || !has_right {
+			return IllegalMove::CastlingRightsLost;
+		}
+		let between: &[i8] = if kingside { &[5, 6] } else { &[1, 2, 3] };
+		if between.iter().any(|file| self.board.piece_at((home_rank, *file)).is_some()) {
+			return IllegalMove::PathBlocked;
+		}
+		if self.is_in_check(self.side_to_move) {
+			return IllegalMove::CastlingOutOfCheck;
+		}
+		let passed_over = Square::new(home_rank, if kingside { 5 } else { 3 });
+		if self.is_square_attacked(passed_over, self.side_to_move.other()) {
+			return IllegalMove::CastlingThroughCheck;
+		}
+		IllegalMove::LeavesKingInCheck
+	}
+
+	/// Takes back the last move played, keeping it so that `redo` can replay it. A result that was
+	/// declared by resignation, agreement, claim or time forfeit is withdrawn.
+	pub fn undo(&mut self) -> Option<Move> {
+		self.discard_stale_redos();
+		let (m, undo) = self.undo_stack.pop()?;
+		self.declared_outcome = None;
+		self.restore_move(m, undo);
+		self.redo_stack.push(m);
+		self.redo_base = self.undo_stack.len();
+		Some(m)
+	}
+
+	/// Replays the move most recently taken back by `undo`, unless another move was played since.
+	pub fn redo(&mut self) -> Option<Move> {
+		self.discard_stale_redos();
+		let m = self.redo_stack.pop()?;
+		let undo = self.apply_move(m);
+		self.undo_stack.push((m, undo));
+		self.redo_base = self.undo_stack.len();
+		Some(m)
+	}
+
+	fn discard_stale_redos(&mut self) {
+		if self.undo_stack.len() != self.redo_base {
+			self.redo_stack.clear();
+		}
+	}
+
+	fn apply_move(&mut self, m: Move) -> MoveUndo {
+		let mut undo = MoveUndo {
+			captured: self.board.piece_at(m.to).map(|piece| (piece, m.to)),
+			castling_availability: self.castling_availability,
+			en_passant_square: self.en_passant_square,
+			halfmove_clock: self.halfmove_clock,
+			fullmove_number: self.fullmove_number,
+		};
+		let is_pawn_move = self.board.piece_at(m.from).unwrap().piece_type == PieceType::Pawn;
+		if is_pawn_move || self.board.piece_at(m.to).is_some() {
+			self.halfmove_clock = 0;
+		} else {
+			self.halfmove_clock += 1;
+		}
+		if self.side_to_move == Side::Black {
+			self.fullmove_number += 1;
+		}
+
 		if let PieceType::Pawn = self.board.piece_at(m.from).unwrap().piece_type {
 			if let Some(sq) = self.en_passant_square {
 				if sq == m.to {
 					let pawn_to_remove = (m.from.0, sq.1);
+					undo.captured = self.board.piece_at(pawn_to_remove).map(|piece| (piece, pawn_to_remove));
 					self.board.remove_piece(pawn_to_remove);
 				}
 			}
@@ -364,22 +1320,251 @@ impl GameState {
 				self.board.move_piece(move_the_rook_to_d1_or_d8);
 			}
 		}
-		if m.from == Board::coordinates_from_name("a1") || m.from == Board::coordinates_from_name("e1") {
+		// A move from a king or rook square gives up the right, and so does a capture on a rook square.
+		let touches = |coordinates: (i8, i8)| m.from == coordinates || m.to == coordinates;
+		if touches((0, 0)) || m.from == (0, 4) {
 			self.castling_availability.white_can_castle_queenside = false;
 		}
-		if m.from == Board::coordinates_from_name("h1") || m.from == Board::coordinates_from_name("e1") {
+		if touches((0, 7)) || m.from == (0, 4) {
 			self.castling_availability.white_can_castle_kingside = false;
 		}
-		if m.from == Board::coordinates_from_name("a8") || m.from == Board::coordinates_from_name("e8") {
+		if touches((7, 0)) || m.from == (7, 4) {
 			self.castling_availability.black_can_castle_queenside = false;
 		}
-		if m.from == Board::coordinates_from_name("h8") || m.from == Board::coordinates_from_name("e8") {
+		if touches((7, 7)) || m.from == (7, 4) {
 			self.castling_availability.black_can_castle_kingside = false;
 		}
 
 		self.board.move_piece(m);
 
 		self.side_to_move = Side::other(&self.side_to_move);
+		self.position_history.push(self.position_key());
+		debug_assert_eq!(self.hash(), self.recompute_hash());
+		undo
+	}
+
+	fn restore_move(&mut self, m: Move, undo: MoveUndo) {
+		self.position_history.pop();
+		self.side_to_move = self.side_to_move.other();
+
+		let piece = self.board.piece_at(m.to).unwrap();
+		self.board.remove_piece(m.to);
+		match m.promo {
+			Some(_) => self.board.place_piece(Piece { side: piece.side, piece_type: PieceType::Pawn }, m.from),
+			None => self.board.place_piece(piece, m.from),
+		}
+		if let PieceType::King = piece.piece_type {
+			if m.to.1 - m.from.1 == 2 {
+				self.board.move_piece(Move::new((m.from.0, 5), (m.from.0, 7)));
+			}
+			if m.to.1 - m.from.1 == -2 {
+				self.board.move_piece(Move::new((m.from.0, 3), (m.from.0, 0)));
+			}
+		}
+		if let Some((captured, square)) = undo.captured {
+			self.board.place_piece(captured, square);
+		}
+
+		self.castling_availability = undo.castling_availability;
+		self.en_passant_square = undo.en_passant_square;
+		self.halfmove_clock = undo.halfmove_clock;
+		self.fullmove_number = undo.fullmove_number;
+		debug_assert_eq!(self.hash(), self.recompute_hash());
+	}
+
+	/// A 64-bit Zobrist key for the position: pieces, side to move, castling rights and the file of
+	/// the en passant square, whether or not a capture there is possible. The piece part is kept up
+	/// to date by the board as pieces are placed and removed, so this is cheap to call after every move.
+	pub fn hash(&self) -> u64 {
+		self.board.hash ^ self.state_hash()
+	}
+
+	fn state_hash(&self) -> u64 {
+		let mut hash = self.castling_availability.zobrist_key();
+		if self.side_to_move == Side::White {
+			hash ^= zobrist::side_to_move_key();
+		}
+		if let Some(square) = self.en_passant_square {
+			hash ^= zobrist::en_passant_key(square.1);
+		}
+		hash
+	}
+
+	/// The hash built from nothing, to check the incremental one against.
+	fn recompute_hash(&self) -> u64 {
+		let mut hash = self.state_hash();
+		for rank in 0..8 {
+			for file in 0..8 {
+				if let Some(piece) = self.board.piece_at((rank, file)) {
+					hash ^= zobrist::piece_key(piece, (rank, file));
+				}
+			}
+		}
+		hash
+	}
+
+	/// The result of the game if it has ended, either because a player resigned, claimed or agreed
+	/// a draw or ran out of time, or because the position itself ends the game.
+	pub fn outcome(&self) -> Option<Outcome> {
+		if self.declared_outcome.is_some() {
+			return self.declared_outcome;
+		}
+		let termination = if self.get_legal_moves().is_empty() {
+			if self.is_in_check(self.side_to_move) {
+				return Some(Outcome { winner: Some(self.side_to_move.other()), termination: Termination::Checkmate });
+			}
+			Termination::Stalemate
+		} else if self.has_insufficient_material() {
+			Termination::InsufficientMaterial
+		} else if self.halfmove_clock >= 150 {
+			Termination::SeventyFiveMoves
+		} else if self.is_fivefold_repetition() {
+			Termination::FivefoldRepetition
+		} else {
+			return None;
+		};
+		Some(Outcome { winner: None, termination })
+	}
+
+	pub fn resign(&mut self, side: Side) {
+		self.declared_outcome = Some(Outcome { winner: Some(side.other()), termination: Termination::Resignation });
+	}
+
+	pub fn agree_draw(&mut self) {
+		self.declared_outcome = Some(Outcome { winner: None, termination: Termination::Agreement });
+	}
+
+	/// Records that `side` ran out of time. Their opponent wins unless they cannot possibly win on
+	/// time, in which case the game is drawn.
+	pub fn flag(&mut self, side: Side) {
+		let winner = if self.cannot_win_on_time(side.other()) { None } else { Some(side.other()) };
+		self.declared_outcome = Some(Outcome { winner, termination: Termination::Timeout });
+	}
+
+	/// Claims a draw under the fifty-move or threefold repetition rule. Returns whether the claim
+	/// was valid, in which case the game is over.
+	pub fn claim_draw(&mut self) -> bool {
+		let termination = if self.can_claim_fifty_move_draw() {
+			Termination::FiftyMoves
+		} else if self.can_claim_threefold_repetition() {
+			Termination::ThreefoldRepetition
+		} else {
+			return false;
+		};
+		self.declared_outcome = Some(Outcome { winner: None, termination });
+		true
+	}
+
+	/// FIDE dead position through lack of material: no sequence of legal moves can mate either king.
+	/// That is K vs K, a lone minor piece against a bare king, or only bishops that all stand on
+	/// squares of the same colour.
+	pub fn has_insufficient_material(&self) -> bool {
+		let mut minors = 0;
+		let mut knights = 0;
+		let mut bishop_square_colours = HashSet::new();
+		for side in [Side::White, Side::Black] {
+			for square in self.board.squares_of(side) {
+				match self.board.piece_at(square).unwrap().piece_type {
+					PieceType::King => (),
+					PieceType::Knight => {
+						minors += 1;
+						knights += 1;
+					},
+					PieceType::Bishop => {
+						minors += 1;
+						bishop_square_colours.insert((square.0 + square.1) % 2);
+					},
+					_ => return false,
+				}
+			}
+		}
+		minors <= 1 || (knights == 0 && bishop_square_colours.len() == 1)
+	}
+
+	/// The weaker USCF-style test used when `side` runs out of time: their opponent only wins if
+	/// they have mating material. A bare king, a king and one minor piece, or a king and two knights
+	/// against a bare king can never force mate, so a flag fall against them is a draw.
+	pub fn cannot_win_on_time(&self, side: Side) -> bool {
+		let mut knights = 0;
+		let mut bishops = 0;
+		for square in self.board.squares_of(side) {
+			match self.board.piece_at(square).unwrap().piece_type {
+				PieceType::King => (),
+				PieceType::Knight => knights += 1,
+				PieceType::Bishop => bishops += 1,
+				_ => return false,
+			}
+		}
+		let opponent_has_bare_king = self.board.occupancy(side.other()).count_ones() == 1;
+		knights + bishops <= 1 || (knights == 2 && bishops == 0 && opponent_has_bare_king)
+	}
+
+	fn position_key(&self) -> PositionKey {
+		PositionKey {
+			pieces: self.board.pieces,
+			side_to_move: self.side_to_move,
+			castling_availability: self.castling_availability,
+			en_passant_square: self.legal_en_passant_square(),
+		}
+	}
+
+	/// The en passant square, but only if the side to move has a legal capture onto it.
+	fn legal_en_passant_square(&self) -> Option<(i8, i8)> {
+		let square = self.en_passant_square?;
+		let capturing_rank = match self.side_to_move {
+			Side::White => square.0 - 1,
+			Side::Black => square.0 + 1,
+		};
+		let pawn = Piece { side: self.side_to_move, piece_type: PieceType::Pawn };
+		for origin in [(capturing_rank, square.1 - 1), (capturing_rank, square.1 + 1)] {
+			if Board::within_bounds(origin) && self.board.piece_at(origin) == Some(pawn) && self.en_passant_is_safe(origin, square) {
+				return Some(square);
+			}
+		}
+		None
+	}
+
+	/// Whether capturing en passant from `origin` onto `square` leaves the king out of check. The
+	/// capture is tried on the occupancy bitboard, since it can uncover a check along the rank by
+	/// taking two pawns off it at once.
+	fn en_passant_is_safe(&self, origin: (i8, i8), square: (i8, i8)) -> bool {
+		let king = self.board.king_location(self.side_to_move);
+		if !Board::within_bounds(king) {
+			return true;
+		}
+		let captured = bitboard::bit((origin.0, square.1));
+		let occupied = (self.board.occupied() ^ bitboard::bit(origin) ^ captured) | bitboard::bit(square);
+		self.board.attackers(bitboard::index(king), self.side_to_move.other(), occupied) == 0
+	}
+
+	/// How many times the current position has occurred, counting this occurrence. Only positions
+	/// since the last pawn move or capture are compared, as nothing earlier can repeat.
+	pub fn repetition_count(&self) -> usize {
+		let current = self.position_history.last().unwrap();
+		let window = (self.halfmove_clock as usize + 1).min(self.position_history.len());
+		self.position_history[self.position_history.len() - window..].iter()
+			.filter(|key| *key == current)
+			.count()
+	}
+
+	pub fn can_claim_threefold_repetition(&self) -> bool {
+		self.repetition_count() >= 3
+	}
+
+	/// A position that occurs five times ends the game in a draw without either player claiming it.
+	pub fn is_fivefold_repetition(&self) -> bool {
+		self.repetition_count() >= 5
+	}
+
+	/// A draw may be claimed once fifty moves by each side have passed without a pawn move or a capture.
+	pub fn can_claim_fifty_move_draw(&self) -> bool {
+		self.halfmove_clock >= 100 && !self.is_in_checkmate(self.side_to_move)
+	}
+
+	/// After seventy-five moves by each side without a pawn move or a capture the game is drawn
+	/// automatically, unless the last of those moves delivered checkmate.
+	pub fn is_seventy_five_move_draw(&self) -> bool {
+		self.halfmove_clock >= 150 && !self.is_in_checkmate(self.side_to_move)
 	}
 
 	pub fn make_move_on_copy(&self, m: Move) -> GameState {
@@ -389,14 +1574,8 @@ impl GameState {
 	}
 
 	pub fn is_in_check(&self, side: Side) -> bool {
-		for square in self.board.sides.get(&Side::other(&side)).unwrap() {
-			for m in self.get_possible_moves_from(*square) {
-				if m.to == self.board.king_location(side) {
-					return true;
-				}
-			}
-		}
-		false
+		let king = self.board.king_location(side);
+		Board::within_bounds(king) && self.board.is_attacked_by(king, side.other())
 	}
 
 	pub fn is_in_checkmate(&self, side: Side) -> bool {
@@ -410,28 +1589,114 @@ impl GameState {
 	pub fn move_would_put_self_in_check(&self, m: Move) -> bool {
 		let side_making_move = self.board.piece_at(m.from).unwrap().side;
 		let hypothetical_board = self.make_move_on_copy(m);
-		let check = hypothetical_board.is_in_check(side_making_move);
-		check
+		hypothetical_board.is_in_check(side_making_move)
 	}
 
-	pub fn get_legal_moves(&self) -> Vec<Move> {
-		let mut moves = Vec::new();
-		for candidate in self.get_possible_moves() {
-			if !self.move_would_put_self_in_check(candidate) {
-				moves.push(candidate);
+	/// Every legal move for the side to move. The checkers and pinned pieces are worked out once:
+	/// in double check only the king moves, in single check the other pieces must capture the
+	/// checker or block, and a pinned piece stays on the line between its king and the pinner.
+	pub fn get_legal_moves(&self) -> Vec<Move> {
+		let mut moves = Vec::new();
+		let us = self.side_to_move;
+		let them = us.other();
+		let occupied = self.board.occupied();
+		let ours = self.board.occupancy(us);
+		let theirs = self.board.occupancy(them);
+
+		let king = self.board.king_location(us);
+		let has_king = Board::within_bounds(king);
+		let mut checkers = 0;
+		let mut pinned = 0;
+		let mut pin_rays = [!0; 64];
+		if has_king {
+			let king_square = bitboard::index(king);
+			checkers = self.board.attackers(king_square, them, occupied);
+			let straight_sliders = self.board.pieces(them, PieceType::Rook) | self.board.pieces(them, PieceType::Queen);
+			let diagonal_sliders = self.board.pieces(them, PieceType::Bishop) | self.board.pieces(them, PieceType::Queen);
+			let pinners = bitboard::rook_attacks(king_square, theirs) & straight_sliders | bitboard::bishop_attacks(king_square, theirs) & diagonal_sliders;
+			for pinner in bitboard::squares(pinners) {
+				let pinner_square = bitboard::index(pinner);
+				let line = bitboard::between(king_square, pinner_square);
+				let blockers = line & occupied;
+				if blockers.count_ones() == 1 && blockers & ours != 0 {
+					pinned |= blockers;
+					pin_rays[blockers.trailing_zeros() as usize] = line | bitboard::bit(pinner);
+				}
+			}
+
+			let without_king = occupied ^ bitboard::bit(king);
+			for to in bitboard::squares(bitboard::KING_ATTACKS[king_square] & !ours) {
+				if self.board.attackers(bitboard::index(to), them, without_king) == 0 {
+					moves.push(Move::new(king, to));
+				}
+			}
+		}
+		if checkers.count_ones() > 1 {
+			return moves;
+		}
+		let evasions = match checkers {
+			0 => !0,
+			checker => checker | bitboard::between(bitboard::index(king), checker.trailing_zeros() as usize),
+		};
+		let allowed = |from: (i8, i8)| evasions & !ours & pin_rays[bitboard::index(from)];
+
+		for from in bitboard::squares(self.board.pieces(us, PieceType::Knight) & !pinned) {
+			Self::push_moves(&mut moves, from, bitboard::KNIGHT_ATTACKS[bitboard::index(from)] & allowed(from), false);
+		}
+		for from in bitboard::squares(self.board.pieces(us, PieceType::Bishop)) {
+			Self::push_moves(&mut moves, from, bitboard::bishop_attacks(bitboard::index(from), occupied) & allowed(from), false);
+		}
+		for from in bitboard::squares(self.board.pieces(us, PieceType::Rook)) {
+			Self::push_moves(&mut moves, from, bitboard::rook_attacks(bitboard::index(from), occupied) & allowed(from), false);
+		}
+		for from in bitboard::squares(self.board.pieces(us, PieceType::Queen)) {
+			Self::push_moves(&mut moves, from, bitboard::queen_attacks(bitboard::index(from), occupied) & allowed(from), false);
+		}
+
+		let (ahead, initial_rank, last_rank) = match us {
+			Side::White => (1, 1, 7),
+			Side::Black => (-1, 6, 0),
+		};
+		for from in bitboard::squares(self.board.pieces(us, PieceType::Pawn)) {
+			let mut targets = bitboard::PAWN_ATTACKS[us as usize][bitboard::index(from)] & theirs;
+			let one_ahead = (from.0 + ahead, from.1);
+			if occupied & bitboard::bit(one_ahead) == 0 {
+				targets |= bitboard::bit(one_ahead);
+				let two_ahead = (from.0 + 2 * ahead, from.1);
+				if from.0 == initial_rank && occupied & bitboard::bit(two_ahead) == 0 {
+					targets |= bitboard::bit(two_ahead);
+				}
+			}
+			Self::push_moves(&mut moves, from, targets & allowed(from), one_ahead.0 == last_rank);
+			if let Some(square) = self.en_passant_square {
+				if bitboard::PAWN_ATTACKS[us as usize][bitboard::index(from)] & bitboard::bit(square) != 0 && self.en_passant_is_safe(from, square) {
+					moves.push(Move::new(from, square));
+				}
 			}
 		}
-		for candidate in self.get_castling_moves() {
-			moves.push(candidate);
+
+		if checkers == 0 && has_king {
+			moves.append(&mut self.get_castling_moves());
 		}
 		moves
 	}
 
+	fn push_moves(moves: &mut Vec<Move>, from: (i8, i8), targets: Bitboard, promotes: bool) {
+		for to in bitboard::squares(targets) {
+			if promotes {
+				for promo_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+					moves.push(Move::new_with_promo(from, to, promo_type));
+				}
+			} else {
+				moves.push(Move::new(from, to));
+			}
+		}
+	}
+
 	pub fn get_possible_moves(&self) -> Vec<Move> {
 		let mut moves = Vec::new();
-		let origin_squares = self.board.sides.get(&self.side_to_move).unwrap();
-		for origin in origin_squares {
-			moves.append(&mut self.get_possible_moves_from(*origin));
+		for origin in self.board.squares_of(self.side_to_move) {
+			moves.append(&mut self.get_possible_moves_from(origin));
 		}
 		moves
 	}
@@ -456,19 +1721,15 @@ impl GameState {
 					let one_ahead = (rank + direction, file);
 					let two_ahead = (rank + (direction * 2), file);
 					let on_seventh = !Board::within_bounds(two_ahead);
-					if let None = self.board.piece_at(one_ahead) {
+					if self.board.piece_at(one_ahead).is_none() {
 						if on_seventh {
 							for promo_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
 								moves.push(Move::new_with_promo(current, one_ahead, promo_type));
 							}
 						} else {
 							moves.push(Move::new(current, one_ahead));
-							if Board::within_bounds(two_ahead) {
-								if let None = self.board.piece_at(two_ahead) {
-									if on_initial_rank {
-										moves.push(Move::new(current, two_ahead));
-									}
-								}	
+							if on_initial_rank && Board::within_bounds(two_ahead) && self.board.piece_at(two_ahead).is_none() {
+								moves.push(Move::new(current, two_ahead));
 							}
 						}
 					}
@@ -580,67 +1841,237 @@ impl GameState {
 		moves
 	}
 
+	/// Castling moves for a side to move that isn't in check. The squares between king and rook must
+	/// be empty, and the king may not pass over or land on an attacked square.
 	fn get_castling_moves(&self) -> Vec<Move> {
 		let mut moves = Vec::new();
-		let king_initial_square = match self.side_to_move {
-			Side::White => Board::coordinates_from_name("e1"),
-			Side::Black => Board::coordinates_from_name("e8"),
+		let us = self.side_to_move;
+		let (home_rank, kingside, queenside) = match us {
+			Side::White => (0, self.castling_availability.white_can_castle_kingside, self.castling_availability.white_can_castle_queenside),
+			Side::Black => (7, self.castling_availability.black_can_castle_kingside, self.castling_availability.black_can_castle_queenside),
 		};
-		if self.is_in_check(self.side_to_move) {
+		let king = (home_rank, 4);
+		if self.board.piece_at(king) != Some(Piece { side: us, piece_type: PieceType::King }) {
 			return moves;
 		}
-		if let Some(piece) = self.board.piece_at(king_initial_square) {
-			if let PieceType::King = piece.piece_type {
-				match piece.side {
-					Side::White => {
-						if self.castling_availability.white_can_castle_kingside {
-							let kf1 = Move::from_str("e1-f1");
-							let kg1 = Move::from_str("e1-g1");
-							if (self.board.piece_at_square_name("f1") == None && !self.move_would_put_self_in_check(kf1)) &&
-								(self.board.piece_at_square_name("g1") == None && !self.move_would_put_self_in_check(kg1)) {
-								moves.push(kg1);
-							}
-						}
-						if self.castling_availability.white_can_castle_queenside {
-							let kd1 = Move::from_str("e1-d1");
-							let kc1 = Move::from_str("e1-c1");
-							if (self.board.piece_at_square_name("d1") == None && !self.move_would_put_self_in_check(kd1)) &&
-								(self.board.piece_at_square_name("c1") == None && !self.move_would_put_self_in_check(kc1)) &&
-								self.board.piece_at_square_name("b1") == None {
-								moves.push(kc1);
-							}
-						}
-					},
-					Side::Black => {
-						if self.castling_availability.black_can_castle_kingside {
-							let kf8 = Move::from_str("e8-f8");
-							let kg8 = Move::from_str("e8-g8");
-							if (self.board.piece_at_square_name("f8") == None && !self.move_would_put_self_in_check(kf8)) &&
-								(self.board.piece_at_square_name("g8") == None && !self.move_would_put_self_in_check(kg8)) {
-								moves.push(kg8);
-							}
-						}
-						if self.castling_availability.black_can_castle_queenside {
-							let kd8 = Move::from_str("e8-d8");
-							let kc8 = Move::from_str("e8-c8");
-							if (self.board.piece_at_square_name("d8") == None && !self.move_would_put_self_in_check(kd8)) &&
-								(self.board.piece_at_square_name("c8") == None && !self.move_would_put_self_in_check(kc8)) && 
-								self.board.piece_at_square_name("b8") == None {
-								moves.push(kc8);
-							}
-						}
-					},
+		let occupied = self.board.occupied();
+		let empty = |files: &[i8]| files.iter().all(|file| occupied & bitboard::bit((home_rank, *file)) == 0);
+		let safe = |file: i8| self.board.attackers(bitboard::index((home_rank, file)), us.other(), occupied ^ bitboard::bit(king)) == 0;
+		if kingside && empty(&[5, 6]) && safe(5) && safe(6) {
+			moves.push(Move::new(king, (home_rank, 6)));
+		}
+		if queenside && empty(&[1, 2, 3]) && safe(3) && safe(2) {
+			moves.push(Move::new(king, (home_rank, 2)));
+		}
+		moves
+	}
+
+	/// Writes a legal move in Standard Algebraic Notation, e.g. "Nf3", "exd5", "O-O-O", "e8=Q+" or
+	/// "Rad1#". The origin is only given where another piece of the same type could also move there.
+	pub fn move_to_san(&self, m: Move) -> String {
+		let piece = self.board.piece_at(m.from).unwrap();
+		let mut san = String::new();
+		if piece.piece_type == PieceType::King && (m.to.1 - m.from.1).abs() == 2 {
+			san.push_str(if m.to.1 > m.from.1 { "O-O" } else { "O-O-O" });
+		} else {
+			let is_capture = self.board.piece_at(m.to).is_some() || (piece.piece_type == PieceType::Pawn && m.from.1 != m.to.1);
+			if piece.piece_type == PieceType::Pawn {
+				if is_capture {
+					san.push((b'a' + m.from.1 as u8) as char);
+				}
+			} else {
+				san.push(piece.piece_type.to_char().to_ascii_uppercase());
+				let rivals: Vec<Move> = self.get_legal_moves().into_iter()
+					.filter(|other| other.to == m.to && other.from != m.from && self.board.piece_at(other.from) == Some(piece))
+					.collect();
+				if !rivals.is_empty() {
+					let file_is_unique = rivals.iter().all(|other| other.from.1 != m.from.1);
+					let rank_is_unique = rivals.iter().all(|other| other.from.0 != m.from.0);
+					let name = Board::name_from_coordinates(m.from);
+					if file_is_unique {
+						san.push_str(&name[..1]);
+					} else if rank_is_unique {
+						san.push_str(&name[1..]);
+					} else {
+						san.push_str(&name);
+					}
 				}
 			}
+			if is_capture {
+				san.push('x');
+			}
+			san.push_str(&Board::name_from_coordinates(m.to));
+			if let Some(promo_type) = m.promo {
+				san.push('=');
+				san.push(promo_type.to_char().to_ascii_uppercase());
+			}
 		}
-		moves
+
+		let after = self.make_move_on_copy(m);
+		if after.is_in_check(after.side_to_move) {
+			san.push(if after.get_legal_moves().is_empty() { '#' } else { '+' });
+		}
+		san
+	}
+
+	/// Finds the legal move meant by a SAN string. Check and annotation suffixes are ignored, and
+	/// common sloppiness is accepted: "0-0" for castling, lowercase piece letters, a missing "="
+	/// before the promotion piece and a fully specified origin square.
+	pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+		let trimmed = san.trim().trim_end_matches(['+', '#', '!', '?']);
+		if trimmed.is_empty() {
+			return Err(SanError::Empty);
+		}
+		let legal_moves = self.get_legal_moves();
+
+		let castling_direction = match trimmed {
+			"O-O" | "0-0" | "o-o" => Some(1),
+			"O-O-O" | "0-0-0" | "o-o-o" => Some(-1),
+			_ => None,
+		};
+		if let Some(direction) = castling_direction {
+			return legal_moves.into_iter()
+				.find(|m| m.from == self.board.king_location(self.side_to_move) && m.to.1 - m.from.1 == 2 * direction)
+				.ok_or_else(|| SanError::NoMatchingMove(String::from(san)));
+		}
+
+		let mut patterns = Vec::new();
+		if let Some(pattern) = SanPattern::parse(trimmed) {
+			patterns.push(pattern);
+		}
+		let mut chars = trimmed.chars();
+		if let Some(first @ ('n' | 'b' | 'r' | 'q' | 'k')) = chars.next() {
+			let capitalized = format!("{}{}", first.to_ascii_uppercase(), chars.as_str());
+			if let Some(pattern) = SanPattern::parse(&capitalized) {
+				patterns.push(pattern);
+			}
+		}
+		if patterns.is_empty() {
+			return Err(SanError::Syntax(String::from(san)));
+		}
+
+		for pattern in patterns {
+			let matching: Vec<Move> = legal_moves.iter().copied().filter(|m| pattern.matches(self, *m)).collect();
+			match matching.len() {
+				0 => continue,
+				1 => return Ok(matching[0]),
+				_ => return Err(SanError::Ambiguous(String::from(san))),
+			}
+		}
+		Err(SanError::NoMatchingMove(String::from(san)))
 	}
 
+	/// Whether `candidate` is one of `get_legal_moves`, so the side to move, checks and castling
+	/// rules are all taken into account.
 	pub fn move_is_legal(&self, candidate: Move) -> bool {
-		match self.board.piece_at(candidate.from) {
-			None => false,
-			Some(_p) => self.get_possible_moves_from(candidate.from).contains(&candidate),
+		self.check_move(candidate).is_ok()
+	}
+}
+
+/// Sets up a position piece by piece. It starts from an empty board with White to move, no
+/// castling rights, no en passant square and clocks at 0 and 1; `build` checks the result with
+/// `GameState::validate`.
+#[derive(Clone)]
+pub struct PositionBuilder {
+	squares: [[Option<Piece>; 8]; 8],
+	side_to_move: Side,
+	castling_availability: CastlingAvailability,
+	en_passant_square: Option<Square>,
+	halfmove_clock: u32,
+	fullmove_number: u32,
+	off_board: Vec<Square>,
+}
+
+impl Default for PositionBuilder {
+	fn default() -> PositionBuilder {
+		PositionBuilder::new()
+	}
+}
+
+impl PositionBuilder {
+	pub fn new() -> PositionBuilder {
+		PositionBuilder {
+			squares: [[None; 8]; 8],
+			side_to_move: Side::White,
+			castling_availability: CastlingAvailability::none(),
+			en_passant_square: None,
+			halfmove_clock: 0,
+			fullmove_number: 1,
+			off_board: Vec::new(),
+		}
+	}
+
+	/// Puts `piece` on `square`, replacing whatever was there. A square off the board is reported
+	/// by `build`.
+	pub fn place(mut self, piece: Piece, square: Square) -> PositionBuilder {
+		if let Some(contents) = self.square_mut(square) {
+			*contents = Some(piece);
+		}
+		self
+	}
+
+	pub fn clear(mut self, square: Square) -> PositionBuilder {
+		if let Some(contents) = self.square_mut(square) {
+			*contents = None;
+		}
+		self
+	}
+
+	fn square_mut(&mut self, square: Square) -> Option<&mut Option<Piece>> {
+		if !Board::within_bounds(square.coordinates()) {
+			self.off_board.push(square);
+			return None;
+		}
+		Some(&mut self.squares[square.rank as usize][square.file as usize])
+	}
+
+	pub fn side_to_move(mut self, side: Side) -> PositionBuilder {
+		self.side_to_move = side;
+		self
+	}
+
+	/// Grants or removes one castling right.
+	pub fn castling(mut self, side: Side, kingside: bool, allowed: bool) -> PositionBuilder {
+		let right = match (side, kingside) {
+			(Side::White, true) => &mut self.castling_availability.white_can_castle_kingside,
+			(Side::White, false) => &mut self.castling_availability.white_can_castle_queenside,
+			(Side::Black, true) => &mut self.castling_availability.black_can_castle_kingside,
+			(Side::Black, false) => &mut self.castling_availability.black_can_castle_queenside,
+		};
+		*right = allowed;
+		self
+	}
+
+	pub fn en_passant(mut self, square: Option<Square>) -> PositionBuilder {
+		self.en_passant_square = square;
+		self
+	}
+
+	/// Sets the halfmove clock and fullmove number. The fullmove number starts at 1, and `build`
+	/// rejects 0 as FEN does.
+	pub fn clocks(mut self, halfmove_clock: u32, fullmove_number: u32) -> PositionBuilder {
+		self.halfmove_clock = halfmove_clock;
+		self.fullmove_number = fullmove_number;
+		self
+	}
+
+	pub fn build(mut self) -> Result<GameState, Vec<PositionIssue>> {
+		if let Some(square) = self.en_passant_square.filter(|square| !Board::within_bounds(square.coordinates())) {
+			self.off_board.push(square);
+		}
+		if !self.off_board.is_empty() {
+			return Err(self.off_board.into_iter().map(PositionIssue::SquareOffBoard).collect());
+		}
+		let mut board = Board::new_blank();
+		for (rank, pieces) in self.squares.iter().enumerate() {
+			for (file, piece) in pieces.iter().enumerate() {
+				if let Some(piece) = piece {
+					board.place_piece(*piece, (rank as i8, file as i8));
+				}
+			}
 		}
+		GameState::from_parts(board, self.side_to_move, self.castling_availability, self.en_passant_square.map(Square::coordinates),
+			self.halfmove_clock, self.fullmove_number)
 	}
 }
 
@@ -648,11 +2079,29 @@ impl GameState {
 mod tests {
 	use super::*;
 
+	#[test]
+	fn test_piece_letters() {
+		for side in [Side::White, Side::Black] {
+			for piece_type in PIECE_TYPES {
+				let piece = Piece::new(side, piece_type);
+				assert_eq!(Piece::from_fen_char(piece.to_char()), Some(piece));
+			}
+		}
+		assert_eq!(Piece::new(Side::White, PieceType::Knight).to_char(), 'N');
+		assert_eq!(Piece::new(Side::Black, PieceType::Knight).to_char(), 'n');
+		assert_eq!(Piece::from_fen_char('x'), None);
+	}
+
 	#[test]
 	fn test_coordinates() {
 		assert_eq!("c2", Board::name_from_coordinates((1, 2)));
-		assert_eq!("e6", Board::name_from_coordinates(Board::coordinates_from_name("e6")));
-		assert_eq!("c2-c4", Move::from_str("c2-c4").to_string());
+		assert_eq!("e6", Board::name_from_coordinates(Board::coordinates_from_name("e6").unwrap()));
+		for bad_name in ["", "e", "z9", "e0", "e44", "E4"] {
+			assert_eq!(Board::coordinates_from_name(bad_name), Err(ParseMoveError::InvalidSquare(String::from(bad_name))));
+			assert_eq!(Board::new().piece_at_square_name(bad_name), None);
+			assert!(Board::new().remove_piece_from_square(bad_name).is_err());
+		}
+		assert_eq!("c2-c4", Move::from_str("c2-c4").unwrap().to_string());
 	}
 
 	#[test]
@@ -663,19 +2112,19 @@ mod tests {
 		assert_eq!(game.board.piece_at_square_name("c1").unwrap().side, Side::White);
 		assert_eq!(game.board.piece_at_square_name("f7").unwrap().piece_type, PieceType::Pawn);
 		assert_eq!(game.board.piece_at_square_name("f7").unwrap().side, Side::Black);
-		game.board.remove_piece_from_square("f7");
+		game.board.remove_piece_from_square("f7").unwrap();
 		assert_eq!(game.board.piece_at_square_name("f7"), None);
 	}
 
 	#[test]
 	fn test_making_moves() {
 		let mut game = GameState::new();
-		game.make_move(Move::from_str("e2-e4"));
+		game.make_move(Move::from_str("e2-e4").unwrap());
 		assert_eq!(game.side_to_move, Side::Black);
 		assert_eq!(game.board.piece_at_square_name("e2"), None);
 		assert_eq!(game.board.piece_at_square_name("e4").unwrap().piece_type, PieceType::Pawn);
 		assert_eq!(game.board.piece_at_square_name("e4").unwrap().side, Side::White);
-		game.make_move(Move::from_str("c7-c5"));
+		game.make_move(Move::from_str("c7-c5").unwrap());
 		assert_eq!(game.side_to_move, Side::White);
 		assert_eq!(game.board.piece_at_square_name("c7"), None);
 		assert_eq!(game.board.piece_at_square_name("c5").unwrap().piece_type, PieceType::Pawn);
@@ -685,170 +2134,151 @@ mod tests {
 	#[test]
 	fn test_legal_moves() {
 		let mut game = GameState::new();
-		assert!(game.move_is_legal(Move::from_str("g1-f3")));
-		assert!(game.move_is_legal(Move::from_str("a2-a3")));
-		assert!(game.move_is_legal(Move::from_str("e2-e4")));
-		assert!(game.move_is_legal(Move::from_str("h2-h4")));
-		game.make_move(Move::from_str("d2-d4"));
-		game.make_move(Move::from_str("g8-f6"));
-		assert!(game.move_is_legal(Move::from_str("c1-f4")));
-		assert!(!game.move_is_legal(Move::from_str("f1-c4")));
-		assert!(game.move_is_legal(Move::from_str("d1-d3")));
-		assert!(!game.move_is_legal(Move::from_str("d1-d5")));
-		assert!(!game.move_is_legal(Move::from_str("d1-h5")));
-		game.make_move(Move::from_str("a2-a4"));
-		game.make_move(Move::from_str("g7-g6"));
-		assert!(game.move_is_legal(Move::from_str("a1-a3")));
-		assert!(!game.move_is_legal(Move::from_str("a1-a4")));
-		assert!(game.move_is_legal(Move::from_str("e1-d2")));
-		assert!(!game.move_is_legal(Move::from_str("e1-d1")));
+		assert!(game.move_is_legal(Move::from_str("g1-f3").unwrap()));
+		assert!(game.move_is_legal(Move::from_str("a2-a3").unwrap()));
+		assert!(game.move_is_legal(Move::from_str("e2-e4").unwrap()));
+		assert!(game.move_is_legal(Move::from_str("h2-h4").unwrap()));
+		game.make_move(Move::from_str("d2-d4").unwrap());
+		game.make_move(Move::from_str("g8-f6").unwrap());
+		assert!(game.move_is_legal(Move::from_str("c1-f4").unwrap()));
+		assert!(!game.move_is_legal(Move::from_str("f1-c4").unwrap()));
+		assert!(game.move_is_legal(Move::from_str("d1-d3").unwrap()));
+		assert!(!game.move_is_legal(Move::from_str("d1-d5").unwrap()));
+		assert!(!game.move_is_legal(Move::from_str("d1-h5").unwrap()));
+		game.make_move(Move::from_str("a2-a4").unwrap());
+		game.make_move(Move::from_str("g7-g6").unwrap());
+		assert!(game.move_is_legal(Move::from_str("a1-a3").unwrap()));
+		assert!(!game.move_is_legal(Move::from_str("a1-a4").unwrap()));
+		assert!(game.move_is_legal(Move::from_str("e1-d2").unwrap()));
+		assert!(!game.move_is_legal(Move::from_str("e1-d1").unwrap()));
 	}
 
 	
 	#[test]
 	fn test_pawns() {
 		let mut game = GameState::new();
-		game.make_move(Move::from_str("g1-f3"));
-		game.make_move(Move::from_str("g8-f6"));
-		assert!(game.move_is_legal(Move::from_str("e2-e4")));
-		assert!(!game.move_is_legal(Move::from_str("f2-f4")));
+		game.make_move(Move::from_str("g1-f3").unwrap());
+		game.make_move(Move::from_str("g8-f6").unwrap());
+		assert!(game.move_is_legal(Move::from_str("e2-e4").unwrap()));
+		assert!(!game.move_is_legal(Move::from_str("f2-f4").unwrap()));
 	}
 
 	#[test]
 	fn test_legal_move_generator() {
 		let mut game = GameState::new();
-		assert!(game.get_possible_moves().contains(&Move::from_str("a2-a3")));
-		assert!(game.get_possible_moves().contains(&Move::from_str("a2-a4")));
-		assert!(game.get_possible_moves().contains(&Move::from_str("g1-h3")));
-		assert!(game.get_possible_moves().contains(&Move::from_str("g1-f3")));
-		game.make_move(Move::from_str("e2-e4"));
-		game.make_move(Move::from_str("e7-e5"));
-		assert!(game.get_possible_moves().contains(&Move::from_str("f1-a6")));
-		assert!(game.get_possible_moves().contains(&Move::from_str("d1-g4")));
-		assert!(!game.get_possible_moves().contains(&Move::from_str("f1-h3")));
-		assert!(!game.get_possible_moves().contains(&Move::from_str("f1-g2")));
-		assert!(!game.get_possible_moves().contains(&Move::from_str("d1-d2")));
+		assert!(game.get_possible_moves().contains(&Move::from_str("a2-a3").unwrap()));
+		assert!(game.get_possible_moves().contains(&Move::from_str("a2-a4").unwrap()));
+		assert!(game.get_possible_moves().contains(&Move::from_str("g1-h3").unwrap()));
+		assert!(game.get_possible_moves().contains(&Move::from_str("g1-f3").unwrap()));
+		game.make_move(Move::from_str("e2-e4").unwrap());
+		game.make_move(Move::from_str("e7-e5").unwrap());
+		assert!(game.get_possible_moves().contains(&Move::from_str("f1-a6").unwrap()));
+		assert!(game.get_possible_moves().contains(&Move::from_str("d1-g4").unwrap()));
+		assert!(!game.get_possible_moves().contains(&Move::from_str("f1-h3").unwrap()));
+		assert!(!game.get_possible_moves().contains(&Move::from_str("f1-g2").unwrap()));
+		assert!(!game.get_possible_moves().contains(&Move::from_str("d1-d2").unwrap()));
 	}
 
 	#[test]
 	fn test_illegal_moves() {
 		let game = GameState::new();
-		assert!(!game.move_is_legal(Move::from_str("g1-g3")));
-		assert!(!game.move_is_legal(Move::from_str("g1-e2")));
-		assert!(!game.move_is_legal(Move::from_str("a2-b3")));
-		assert!(!game.move_is_legal(Move::from_str("e2-e2")));
-		assert!(!game.move_is_legal(Move::from_str("e2-e5")));
+		assert!(!game.move_is_legal(Move::from_str("g1-g3").unwrap()));
+		assert!(!game.move_is_legal(Move::from_str("g1-e2").unwrap()));
+		assert!(!game.move_is_legal(Move::from_str("a2-b3").unwrap()));
+		assert!(!game.move_is_legal(Move::from_str("e2-e2").unwrap()));
+		assert!(!game.move_is_legal(Move::from_str("e2-e5").unwrap()));
 	}
 
 	#[test]
 	fn test_check() {
 		let mut game = GameState::new();
-		game.make_move(Move::from_str("e2-e4"));
-		game.make_move(Move::from_str("e7-e5"));
-		game.make_move(Move::from_str("f2-f4"));
+		game.make_move(Move::from_str("e2-e4").unwrap());
+		game.make_move(Move::from_str("e7-e5").unwrap());
+		game.make_move(Move::from_str("f2-f4").unwrap());
 		assert!(!game.is_in_check(Side::White));
-		game.make_move(Move::from_str("d8-h4"));
+		game.make_move(Move::from_str("d8-h4").unwrap());
 		assert!(game.is_in_check(Side::White));
-		game.make_move(Move::from_str("g2-g3"));
+		game.make_move(Move::from_str("g2-g3").unwrap());
 		assert!(!game.is_in_check(Side::White));
 	}
 
 	#[test]
 	fn test_checkmate() {
 		let mut game = GameState::new();
-		game.make_move(Move::from_str("e2-e4"));
-		game.make_move(Move::from_str("e7-e5"));
-		game.make_move(Move::from_str("d1-h5"));
-		game.make_move(Move::from_str("b8-c6"));
-		game.make_move(Move::from_str("f1-c4"));
-		game.make_move(Move::from_str("g8-f6"));
-		game.make_move(Move::from_str("h5-f7"));
+		game.make_move(Move::from_str("e2-e4").unwrap());
+		game.make_move(Move::from_str("e7-e5").unwrap());
+		game.make_move(Move::from_str("d1-h5").unwrap());
+		game.make_move(Move::from_str("b8-c6").unwrap());
+		game.make_move(Move::from_str("f1-c4").unwrap());
+		game.make_move(Move::from_str("g8-f6").unwrap());
+		game.make_move(Move::from_str("h5-f7").unwrap());
 		assert!(game.is_in_checkmate(Side::Black));
 	}
 
 	#[test]
 	fn test_checkmate2() {
 		let mut game = GameState::new();
-		game.make_move(Move::from_str("f2-f3"));
-		game.make_move(Move::from_str("e7-e5"));
-		game.make_move(Move::from_str("g2-g4"));
-		game.make_move(Move::from_str("d8-h4"));
+		game.make_move(Move::from_str("f2-f3").unwrap());
+		game.make_move(Move::from_str("e7-e5").unwrap());
+		game.make_move(Move::from_str("g2-g4").unwrap());
+		game.make_move(Move::from_str("d8-h4").unwrap());
 		assert!(game.is_in_checkmate(Side::White));
 	}
 
 	#[test]
 	fn test_checkmate3() {
-		let mut game = GameState {
-			board: Board::new_blank(),
-			side_to_move: Side::Black,
-			castling_availability: CastlingAvailability::none(),
-			en_passant_square: None,			
-		};
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::Rook, side: Side::White}, "a8");
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::King, side: Side::White}, "g1");
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::King, side: Side::Black}, "g8");
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::Pawn, side: Side::Black}, "f7");
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::Pawn, side: Side::Black}, "g7");
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::Pawn, side: Side::Black}, "h7");
+		let mut game = GameState::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1").unwrap();
 		assert!(game.is_in_checkmate(Side::Black));
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::Bishop, side: Side::Black}, "e4");
+		game.board.place_piece_on_square(Piece { piece_type: PieceType::Bishop, side: Side::Black}, "e4").unwrap();
 		assert!(!game.is_in_checkmate(Side::Black));
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::Pawn, side: Side::Black}, "d5");
+		game.board.place_piece_on_square(Piece { piece_type: PieceType::Pawn, side: Side::Black}, "d5").unwrap();
 		assert!(game.is_in_checkmate(Side::Black));
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::Knight, side: Side::Black}, "b6");
+		game.board.place_piece_on_square(Piece { piece_type: PieceType::Knight, side: Side::Black}, "b6").unwrap();
 		assert!(!game.is_in_checkmate(Side::Black));
 	}
 
 	#[test]
 	fn test_stalemate() {
-		let mut game = GameState {
-			board: Board::new_blank(),
-			side_to_move: Side::Black,
-			castling_availability: CastlingAvailability::none(),
-			en_passant_square: None,			
-		};
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::King, side: Side::White}, "h1");
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::Queen, side: Side::White}, "c7");
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::King, side: Side::Black}, "a8");
+		let game = GameState::from_fen("k7/2Q5/8/8/8/8/8/7K b - - 0 1").unwrap();
 		assert!(game.is_in_stalemate());
 	}
 
 	#[test]
 	fn test_en_passant() {
 		let mut game = GameState::new();
-		game.make_move(Move::from_str("e2-e4"));
-		game.make_move(Move::from_str("a7-a6"));
-		game.make_move(Move::from_str("e4-e5"));
-		game.make_move(Move::from_str("d7-d5"));
-		assert!(game.get_legal_moves().contains(&Move::from_str("e5-d6")));
-		game.make_move(Move::from_str("e5-d6"));
+		game.make_move(Move::from_str("e2-e4").unwrap());
+		game.make_move(Move::from_str("a7-a6").unwrap());
+		game.make_move(Move::from_str("e4-e5").unwrap());
+		game.make_move(Move::from_str("d7-d5").unwrap());
+		assert!(game.get_legal_moves().contains(&Move::from_str("e5-d6").unwrap()));
+		game.make_move(Move::from_str("e5-d6").unwrap());
 		assert_eq!(game.board.piece_at_square_name("d5"), None);
 	}
 
 	#[test]
 	fn test_en_passant2() {
 		let mut game = GameState::new();
-		game.make_move(Move::from_str("e2-e4"));
-		game.make_move(Move::from_str("a7-a6"));
-		game.make_move(Move::from_str("e4-e5"));
-		game.make_move(Move::from_str("f7-f5"));
-		assert!(game.get_legal_moves().contains(&Move::from_str("e5-f6")));
-		game.make_move(Move::from_str("e5-f6"));
+		game.make_move(Move::from_str("e2-e4").unwrap());
+		game.make_move(Move::from_str("a7-a6").unwrap());
+		game.make_move(Move::from_str("e4-e5").unwrap());
+		game.make_move(Move::from_str("f7-f5").unwrap());
+		assert!(game.get_legal_moves().contains(&Move::from_str("e5-f6").unwrap()));
+		game.make_move(Move::from_str("e5-f6").unwrap());
 		assert_eq!(game.board.piece_at_square_name("f5"), None);
 	}
 
 	#[test]
 	fn test_castling() {
 		let mut game = GameState::new();
-		game.make_move(Move::from_str("e2-e4"));
-		game.make_move(Move::from_str("e7-e5"));
-		game.make_move(Move::from_str("g1-f3"));
-		game.make_move(Move::from_str("b8-c6"));
-		assert!(!game.get_legal_moves().contains(&Move::from_str("e1-g1")));
-		game.make_move(Move::from_str("f1-c4"));
-		game.make_move(Move::from_str("f8-c5"));
-		assert!(game.get_legal_moves().contains(&Move::from_str("e1-g1")));
-		game.make_move(Move::from_str("e1-g1"));
+		game.make_move(Move::from_str("e2-e4").unwrap());
+		game.make_move(Move::from_str("e7-e5").unwrap());
+		game.make_move(Move::from_str("g1-f3").unwrap());
+		game.make_move(Move::from_str("b8-c6").unwrap());
+		assert!(!game.get_legal_moves().contains(&Move::from_str("e1-g1").unwrap()));
+		game.make_move(Move::from_str("f1-c4").unwrap());
+		game.make_move(Move::from_str("f8-c5").unwrap());
+		assert!(game.get_legal_moves().contains(&Move::from_str("e1-g1").unwrap()));
+		game.make_move(Move::from_str("e1-g1").unwrap());
 		assert_eq!(game.board.piece_at_square_name("g1").unwrap().piece_type, PieceType::King);
 		assert_eq!(game.board.piece_at_square_name("f1").unwrap().piece_type, PieceType::Rook);
 		assert_eq!(game.board.piece_at_square_name("h1"), None);
@@ -857,20 +2287,20 @@ mod tests {
 	#[test]
 	fn test_castling2() {
 		let mut game = GameState::new();
-		game.make_move(Move::from_str("d2-d4"));
-		game.make_move(Move::from_str("g8-f6"));
-		game.make_move(Move::from_str("b1-c3"));
-		game.make_move(Move::from_str("g7-g6"));
-		game.make_move(Move::from_str("c1-f4"));
-		game.make_move(Move::from_str("f8-g7"));
-		game.make_move(Move::from_str("d1-d2"));
-		assert!(game.get_legal_moves().contains(&Move::from_str("e8-g8")));
-		game.make_move(Move::from_str("e8-g8"));
+		game.make_move(Move::from_str("d2-d4").unwrap());
+		game.make_move(Move::from_str("g8-f6").unwrap());
+		game.make_move(Move::from_str("b1-c3").unwrap());
+		game.make_move(Move::from_str("g7-g6").unwrap());
+		game.make_move(Move::from_str("c1-f4").unwrap());
+		game.make_move(Move::from_str("f8-g7").unwrap());
+		game.make_move(Move::from_str("d1-d2").unwrap());
+		assert!(game.get_legal_moves().contains(&Move::from_str("e8-g8").unwrap()));
+		game.make_move(Move::from_str("e8-g8").unwrap());
 		assert_eq!(game.board.piece_at_square_name("g8").unwrap().piece_type, PieceType::King);
 		assert_eq!(game.board.piece_at_square_name("f8").unwrap().piece_type, PieceType::Rook);
 		assert_eq!(game.board.piece_at_square_name("h8"), None);
-		assert!(game.get_legal_moves().contains(&Move::from_str("e1-c1")));
-		game.make_move(Move::from_str("e1-c1"));
+		assert!(game.get_legal_moves().contains(&Move::from_str("e1-c1").unwrap()));
+		game.make_move(Move::from_str("e1-c1").unwrap());
 		assert_eq!(game.board.piece_at_square_name("c1").unwrap().piece_type, PieceType::King);
 		assert_eq!(game.board.piece_at_square_name("d1").unwrap().piece_type, PieceType::Rook);
 		assert_eq!(game.board.piece_at_square_name("a1"), None);
@@ -879,37 +2309,730 @@ mod tests {
 	#[test]
 	fn test_castling3() {
 		let mut game = GameState::new();
-		game.make_move(Move::from_str("e2-e4"));
-		game.make_move(Move::from_str("e7-e5"));
-		game.make_move(Move::from_str("g1-f3"));
-		game.make_move(Move::from_str("g8-f6"));
-		game.make_move(Move::from_str("f1-c4"));
-		game.make_move(Move::from_str("h7-h6"));
-		game.make_move(Move::from_str("d2-d3"));
-		game.make_move(Move::from_str("f8-b4"));
+		game.make_move(Move::from_str("e2-e4").unwrap());
+		game.make_move(Move::from_str("e7-e5").unwrap());
+		game.make_move(Move::from_str("g1-f3").unwrap());
+		game.make_move(Move::from_str("g8-f6").unwrap());
+		game.make_move(Move::from_str("f1-c4").unwrap());
+		game.make_move(Move::from_str("h7-h6").unwrap());
+		game.make_move(Move::from_str("d2-d3").unwrap());
+		game.make_move(Move::from_str("f8-b4").unwrap());
 		assert!(game.is_in_check(Side::White));
-		assert!(!game.get_legal_moves().contains(&Move::from_str("e1-g1")));
-		game.make_move(Move::from_str("c2-c3"));
-		game.make_move(Move::from_str("b4-a5"));
-		assert!(game.get_legal_moves().contains(&Move::from_str("e1-g1")));
+		assert!(!game.get_legal_moves().contains(&Move::from_str("e1-g1").unwrap()));
+		game.make_move(Move::from_str("c2-c3").unwrap());
+		game.make_move(Move::from_str("b4-a5").unwrap());
+		assert!(game.get_legal_moves().contains(&Move::from_str("e1-g1").unwrap()));
 	}
 
 	#[test]
 	fn test_promotion() {
-		let mut game = GameState {
-			board: Board::new_blank(),
-			side_to_move: Side::White,
-			castling_availability: CastlingAvailability::none(),
-			en_passant_square: None,			
-		};
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::Pawn, side: Side::White}, "a7");
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::King, side: Side::White}, "b7");
-		game.board.place_piece_on_square(Piece { piece_type: PieceType::King, side: Side::Black}, "d7");
+		let mut game = GameState::from_fen("8/PK1k4/8/8/8/8/8/8 w - - 0 1").unwrap();
 		println!("{:?}", game.get_legal_moves());
-		let a8q = Move{from: Board::coordinates_from_name("a7"), to: Board::coordinates_from_name("a8"), promo: Some(PieceType::Queen)};
+		let a8q = Move{from: Board::coordinates_from_name("a7").unwrap(), to: Board::coordinates_from_name("a8").unwrap(), promo: Some(PieceType::Queen)};
 		assert!(game.get_legal_moves().contains(&a8q));
-		assert!(!game.get_legal_moves().contains(&Move::from_str("a7-a8")));
+		assert!(!game.get_legal_moves().contains(&Move::from_str("a7-a8").unwrap()));
 		game.make_move(a8q);
 		assert_eq!(game.board.piece_at_square_name("a8").unwrap().piece_type, PieceType::Queen);
 	}
-}
\ No newline at end of file
+
+	#[test]
+	fn test_from_fen() {
+		let game = GameState::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPB1PPP/R3K2R b Kq e3 12 34").unwrap();
+		assert_eq!(game.side_to_move, Side::Black);
+		assert_eq!(game.castling_availability.to_string(), "Kq");
+		assert_eq!(game.en_passant_square, Some(Board::coordinates_from_name("e3").unwrap()));
+		assert_eq!(game.halfmove_clock, 12);
+		assert_eq!(game.fullmove_number, 34);
+		assert_eq!(game.board.piece_at_square_name("a8").unwrap(), Piece { piece_type: PieceType::Rook, side: Side::Black });
+		assert_eq!(game.board.piece_at_square_name("f3").unwrap(), Piece { piece_type: PieceType::Queen, side: Side::White });
+		assert_eq!(game.board.piece_at_square_name("h3").unwrap(), Piece { piece_type: PieceType::Pawn, side: Side::Black });
+		assert_eq!(game.board.piece_at_square_name("b8"), None);
+		assert_eq!(game.board.king_location(Side::White), Board::coordinates_from_name("e1").unwrap());
+		assert_eq!(game.board.king_location(Side::Black), Board::coordinates_from_name("e8").unwrap());
+
+		let start = GameState::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -").unwrap();
+		assert_eq!(start.board.to_fen_grid(), GameState::new().board.to_fen_grid());
+		assert_eq!(start.halfmove_clock, 0);
+		assert_eq!(start.fullmove_number, 1);
+	}
+
+	#[test]
+	fn test_from_fen_errors() {
+		let error = |fen: &str| GameState::from_fen(fen).err().unwrap();
+		assert_eq!(error(""), FenError::MissingField(FenField::Placement));
+		assert_eq!(error("8/8/8/8/8/8/8/K6k w"), FenError::MissingField(FenField::Castling));
+		assert_eq!(error("8/8/8/8/8/8/K6k w - -"), FenError::WrongRankCount(7));
+		assert_eq!(error("8/8/8/8/8/8/8/K7k w - -"), FenError::BadRankLength { rank: 1, length: 9 });
+		assert_eq!(error("8/8/8/8/8/8/8/K5k w - -"), FenError::BadRankLength { rank: 1, length: 7 });
+		assert_eq!(error("8/8/8/8/8/8/8/K33k w - -"), FenError::ConsecutiveDigits { rank: 1 });
+		assert_eq!(error("8/8/8/3X4/8/8/8/K6k w - -"), FenError::UnknownPiece { rank: 5, letter: 'X' });
+		assert_eq!(error("8/8/8/8/8/8/8/K6k x - -"), FenError::InvalidSideToMove(String::from("x")));
+		assert_eq!(error("8/8/8/8/8/8/8/K6k w KK -"), FenError::InvalidCastling(String::from("KK")));
+		assert_eq!(error("8/8/8/8/8/8/8/K6k w KQz -"), FenError::InvalidCastling(String::from("KQz")));
+		assert_eq!(error("8/8/8/8/8/8/8/K6k w - e4"), FenError::InvalidEnPassant(String::from("e4")));
+		assert_eq!(error("8/8/8/8/8/8/8/K6k w - i6"), FenError::InvalidEnPassant(String::from("i6")));
+		assert_eq!(error("8/8/8/8/8/8/8/K6k w - e"), FenError::InvalidEnPassant(String::from("e")));
+		assert_eq!(error("8/8/8/8/8/8/8/K6k w - - -1 1"), FenError::InvalidHalfmoveClock(String::from("-1")));
+		assert_eq!(error("8/8/8/8/8/8/8/K6k w - - 0 0"), FenError::InvalidFullmoveNumber(String::from("0")));
+		assert_eq!(error("8/8/8/8/8/8/8/K6k w - - 0 1 x"), FenError::TooManyFields(7));
+		assert_eq!(error("8/8/8/8/8/8/8/K6k w - e4").field(), FenField::EnPassant);
+		assert_eq!(error("8/8/8/3X4/8/8/8/K6k w - -").to_string(), "piece placement: unknown piece letter 'X' on rank 5");
+	}
+
+	#[test]
+	fn test_position_builder() {
+		let square = |name: &str| name.parse::<Square>().unwrap();
+		let game = PositionBuilder::new()
+			.place(Piece::new(Side::White, PieceType::King), square("e1"))
+			.place(Piece::new(Side::White, PieceType::Rook), square("h1"))
+			.place(Piece::new(Side::White, PieceType::Queen), square("d4"))
+			.place(Piece::new(Side::Black, PieceType::King), square("e8"))
+			.place(Piece::new(Side::Black, PieceType::Pawn), square("d5"))
+			.clear(square("d4"))
+			.place(Piece::new(Side::White, PieceType::Pawn), square("e4"))
+			.side_to_move(Side::Black)
+			.castling(Side::White, true, true)
+			.en_passant(Some(square("e3")))
+			.clocks(0, 20)
+			.build()
+			.unwrap();
+		assert_eq!(game.to_fen(), "4k3/8/8/3p4/4P3/8/8/4K2R b K e3 0 20");
+		assert!(game.move_is_legal(Move::from_str("d5-e4").unwrap()));
+
+		let piece = Piece::new(Side::Black, PieceType::Knight);
+		assert_eq!((piece.side(), piece.piece_type()), (Side::Black, PieceType::Knight));
+		assert_eq!(PositionBuilder::new().place(Piece::new(Side::White, PieceType::King), square("a1")).build().err(),
+			Some(vec![PositionIssue::MissingKing(Side::Black)]));
+		assert_eq!(PositionBuilder::new()
+			.place(Piece::new(Side::White, PieceType::King), square("e1"))
+			.place(Piece::new(Side::Black, PieceType::King), square("e8"))
+			.castling(Side::Black, false, true)
+			.build().err(),
+			Some(vec![PositionIssue::CastlingWithoutKingAndRook { side: Side::Black, kingside: false }]));
+		let zero_fullmove = PositionBuilder::new()
+			.place(Piece::new(Side::White, PieceType::King), square("e1"))
+			.place(Piece::new(Side::Black, PieceType::King), square("e8"))
+			.clocks(0, 0)
+			.build()
+			.err()
+			.unwrap();
+		assert_eq!(zero_fullmove, vec![PositionIssue::ZeroFullmoveNumber]);
+		assert_eq!(zero_fullmove[0].field(), FenField::FullmoveNumber);
+		let off_board = PositionBuilder::new()
+			.place(Piece::new(Side::White, PieceType::King), square("e1"))
+			.place(Piece::new(Side::Black, PieceType::King), Square::new(8, 0))
+			.clear(Square::new(0, -1))
+			.en_passant(Some(Square::new(2, 8)))
+			.build()
+			.err()
+			.unwrap();
+		assert_eq!(off_board, vec![
+			PositionIssue::SquareOffBoard(Square::new(8, 0)),
+			PositionIssue::SquareOffBoard(Square::new(0, -1)),
+			PositionIssue::SquareOffBoard(Square::new(2, 8)),
+		]);
+		assert_eq!(off_board[0].to_string(), "rank 8 and file 0 are off the board");
+	}
+
+	#[test]
+	fn test_validate_position() {
+		let issues = |fen: &str| match GameState::from_fen(fen) {
+			Err(FenError::InvalidPosition(issues)) => issues,
+			other => panic!("{} loaded as {:?}", fen, other.map(|game| game.to_fen())),
+		};
+		assert_eq!(GameState::new().validate(), Ok(()));
+		assert_eq!(issues("8/8/8/8/8/8/8/K7 w - -"), vec![PositionIssue::MissingKing(Side::Black)]);
+		assert_eq!(issues("k7/8/8/8/8/8/8/K6K w - -"), vec![PositionIssue::TooManyKings(Side::White)]);
+		assert_eq!(issues("k6P/8/8/8/8/8/8/K2p4 w - -"), vec![
+			PositionIssue::PawnOnBackRank(Square::new(0, 3)),
+			PositionIssue::PawnOnBackRank(Square::new(7, 7)),
+		]);
+		assert_eq!(issues("k7/8/8/8/8/8/8/K6r b - -"), vec![PositionIssue::OpponentInCheck(Side::White)]);
+		assert_eq!(issues("r3k2r/8/8/8/8/8/8/R3K1R1 w KQkq -"), vec![PositionIssue::CastlingWithoutKingAndRook { side: Side::White, kingside: true }]);
+		assert_eq!(issues("r4k1r/8/8/8/8/8/8/R3K2R w KQkq -"), vec![
+			PositionIssue::CastlingWithoutKingAndRook { side: Side::Black, kingside: true },
+			PositionIssue::CastlingWithoutKingAndRook { side: Side::Black, kingside: false },
+		]);
+		assert_eq!(issues("4k3/8/8/8/4P3/8/8/4K3 w - e3"), vec![PositionIssue::EnPassantWithoutDoubleStep(Square::new(2, 4))]);
+		assert_eq!(issues("4k3/8/8/8/8/8/8/4K3 b - e3"), vec![PositionIssue::EnPassantWithoutDoubleStep(Square::new(2, 4))]);
+		assert_eq!(issues("4k3/8/8/8/4P3/8/4B3/4K3 b - e3"), vec![PositionIssue::EnPassantWithoutDoubleStep(Square::new(2, 4))]);
+		assert!(GameState::from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3").is_ok());
+
+		let error = GameState::from_fen("7r/8/8/8/8/8/8/K7 b Q -").err().unwrap();
+		assert_eq!(error.field(), FenField::Placement);
+		assert_eq!(error.to_string(), "piece placement: impossible position: Black has no king; White may castle queenside but the king or rook is not on its starting square");
+	}
+
+	#[test]
+	fn test_to_fen() {
+		let mut game = GameState::new();
+		assert_eq!(game.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+		game.make_move(Move::from_str("e2-e4").unwrap());
+		assert_eq!(game.board.to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR");
+		for fen in [
+			"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+			"8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+			"rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2",
+			"r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+			"8/8/8/8/8/8/8/K6k b - - 99 150",
+		] {
+			assert_eq!(GameState::from_fen(fen).unwrap().to_fen(), fen);
+		}
+	}
+
+	#[test]
+	fn test_move_counters() {
+		let mut game = GameState::new();
+		game.make_move(Move::from_str("g1-f3").unwrap());
+		assert_eq!((game.halfmove_clock, game.fullmove_number), (1, 1));
+		game.make_move(Move::from_str("b8-c6").unwrap());
+		assert_eq!((game.halfmove_clock, game.fullmove_number), (2, 2));
+		game.make_move(Move::from_str("e2-e4").unwrap());
+		assert_eq!((game.halfmove_clock, game.fullmove_number), (0, 2));
+		game.make_move(Move::from_str("g8-f6").unwrap());
+		game.make_move(Move::from_str("f1-c4").unwrap());
+		assert_eq!((game.halfmove_clock, game.fullmove_number), (2, 3));
+		game.make_move(Move::from_str("f6-e4").unwrap());
+		assert_eq!((game.halfmove_clock, game.fullmove_number), (0, 4));
+	}
+
+	#[test]
+	fn test_fifty_move_rule() {
+		let mut game = GameState::from_fen("8/8/4k3/8/8/2N5/4K3/8 w - - 98 120").unwrap();
+		assert!(!game.can_claim_fifty_move_draw());
+		game.make_move(Move::from_str("c3-d5").unwrap());
+		assert!(!game.can_claim_fifty_move_draw());
+		game.make_move(Move::from_str("e6-d5").unwrap());
+		assert!(!game.can_claim_fifty_move_draw());
+		assert_eq!(game.halfmove_clock, 0);
+
+		let mut game = GameState::from_fen("8/8/4k3/8/8/2N5/4K3/8 w - - 98 120").unwrap();
+		game.make_move(Move::from_str("c3-b5").unwrap());
+		game.make_move(Move::from_str("e6-d7").unwrap());
+		assert!(game.can_claim_fifty_move_draw());
+		assert!(!game.is_seventy_five_move_draw());
+	}
+
+	#[test]
+	fn test_seventy_five_move_rule() {
+		let mut game = GameState::from_fen("k7/8/1K6/8/8/8/8/7R w - - 149 100").unwrap();
+		game.make_move(Move::from_str("h1-h2").unwrap());
+		assert!(game.is_seventy_five_move_draw());
+
+		let mut game = GameState::from_fen("k7/8/1K6/8/8/8/8/7R w - - 149 100").unwrap();
+		game.make_move(Move::from_str("h1-h8").unwrap());
+		assert!(game.is_in_checkmate(Side::Black));
+		assert!(!game.is_seventy_five_move_draw());
+		assert!(!game.can_claim_fifty_move_draw());
+	}
+
+	fn play(game: &mut GameState, moves: &str) {
+		for m in moves.split_whitespace() {
+			game.make_move(Move::from_str(m).unwrap());
+		}
+	}
+
+	#[test]
+	fn test_threefold_repetition() {
+		let mut game = GameState::new();
+		play(&mut game, "g1-f3 g8-f6 f3-g1 f6-g8");
+		assert_eq!(game.repetition_count(), 2);
+		assert!(!game.can_claim_threefold_repetition());
+		play(&mut game, "g1-f3 g8-f6 f3-g1");
+		assert!(!game.can_claim_threefold_repetition());
+		play(&mut game, "f6-g8");
+		assert!(game.can_claim_threefold_repetition());
+		assert!(!game.is_fivefold_repetition());
+		play(&mut game, "g1-f3 g8-f6 f3-g1 f6-g8");
+		assert_eq!(game.repetition_count(), 4);
+		assert!(!game.is_fivefold_repetition());
+		play(&mut game, "g1-f3 g8-f6 f3-g1 f6-g8");
+		assert!(game.is_fivefold_repetition());
+	}
+
+	#[test]
+	fn test_repetition_needs_same_castling_rights() {
+		let mut game = GameState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+		play(&mut game, "a1-b1 a8-b8 b1-a1 b8-a8");
+		assert_eq!(game.repetition_count(), 1);
+		play(&mut game, "a1-b1 a8-b8 b1-a1 b8-a8");
+		play(&mut game, "a1-b1 a8-b8 b1-a1 b8-a8");
+		assert_eq!(game.repetition_count(), 3);
+	}
+
+	#[test]
+	fn test_repetition_with_unusable_en_passant_square() {
+		// No black pawn can reach e3, so the en passant square left by 1. e4 is irrelevant.
+		let mut game = GameState::new();
+		play(&mut game, "e2-e4 g8-f6 g1-f3 f6-g8 f3-g1 g8-f6 g1-f3 f6-g8 f3-g1");
+		assert!(game.can_claim_threefold_repetition());
+
+		// d4xe3 would expose the black king on a4 to the rook on h4.
+		let mut game = GameState::from_fen("8/8/8/8/k2p3R/8/4P3/4K3 w - - 0 1").unwrap();
+		play(&mut game, "e2-e4 a4-a5 e1-d1 a5-a4 d1-e1 a4-a5 e1-d1 a5-a4 d1-e1");
+		assert!(game.can_claim_threefold_repetition());
+	}
+
+	#[test]
+	fn test_repetition_with_usable_en_passant_square() {
+		let mut game = GameState::from_fen("8/8/8/k7/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+		play(&mut game, "e2-e4 a5-b5 e1-d1 b5-a5 d1-e1 a5-b5 e1-d1 b5-a5 d1-e1");
+		assert_eq!(game.repetition_count(), 2);
+		assert!(!game.can_claim_threefold_repetition());
+		play(&mut game, "a5-b5 e1-d1 b5-a5 d1-e1");
+		assert!(game.can_claim_threefold_repetition());
+	}
+
+	#[test]
+	fn test_insufficient_material() {
+		let dead = |fen: &str| GameState::from_fen(fen).unwrap().has_insufficient_material();
+		assert!(dead("8/8/4k3/8/8/3K4/8/8 w - - 0 1"));
+		assert!(dead("8/8/4k3/8/8/3K4/5B2/8 w - - 0 1"));
+		assert!(dead("8/8/4k3/8/8/3K4/5N2/8 b - - 0 1"));
+		assert!(dead("8/8/4kb2/8/8/3K4/5B2/8 w - - 0 1"));
+		assert!(dead("8/8/4k3/8/8/2BK4/1B3B2/8 w - - 0 1"));
+		assert!(!dead("8/8/4kb2/8/8/3K4/4B3/8 w - - 0 1"));
+		assert!(!dead("8/8/4kn2/8/8/3K4/5N2/8 w - - 0 1"));
+		assert!(!dead("8/8/4k3/8/8/3K4/4NN2/8 w - - 0 1"));
+		assert!(!dead("8/8/4k3/8/8/3K4/5BN1/8 w - - 0 1"));
+		assert!(!dead("8/8/4k3/8/8/3K4/4P3/8 w - - 0 1"));
+		assert!(!dead("8/8/4k3/8/8/3K4/8/7R w - - 0 1"));
+		assert!(!GameState::new().has_insufficient_material());
+	}
+
+	#[test]
+	fn test_cannot_win_on_time() {
+		let game = GameState::from_fen("8/8/4k3/8/8/3K4/5N2/7q w - - 0 1").unwrap();
+		assert!(game.cannot_win_on_time(Side::White));
+		assert!(!game.cannot_win_on_time(Side::Black));
+		assert!(!game.has_insufficient_material());
+		let game = GameState::from_fen("8/8/4k3/8/8/3K4/4NN2/8 w - - 0 1").unwrap();
+		assert!(game.cannot_win_on_time(Side::White));
+		assert!(!game.has_insufficient_material());
+		let game = GameState::from_fen("8/8/4k2p/8/8/3K4/4NN2/8 w - - 0 1").unwrap();
+		assert!(!game.cannot_win_on_time(Side::White));
+		assert!(!game.cannot_win_on_time(Side::Black));
+		let game = GameState::from_fen("8/8/4k3/8/8/3K4/4BB2/8 w - - 0 1").unwrap();
+		assert!(!game.cannot_win_on_time(Side::White));
+	}
+
+	fn assert_same_state(game: &GameState, other: &GameState) {
+		assert_eq!(game.to_fen(), other.to_fen());
+		assert_eq!(game.board.pieces, other.board.pieces);
+		assert_eq!(game.board.occupancy, other.board.occupancy);
+		assert_eq!(game.board.king_location(Side::White), other.board.king_location(Side::White));
+		assert_eq!(game.board.king_location(Side::Black), other.board.king_location(Side::Black));
+		assert_eq!(game.position_history, other.position_history);
+		assert_eq!(game.hash(), other.hash());
+	}
+
+	#[test]
+	fn test_unmake_move() {
+		for fen in [
+			"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+			"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+			"r3k2r/p1ppqpb1/bn2pnp1/3PN3/Pp2P3/2N2Q1p/1PPBBPPP/R3K2R b KQkq a3 0 1",
+			"n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+			"rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+		] {
+			let mut game = GameState::from_fen(fen).unwrap();
+			let before = game.clone();
+			for m in before.get_legal_moves() {
+				let undo = game.make_move(m);
+				game.unmake_move(m, undo);
+				assert_same_state(&game, &before);
+			}
+		}
+	}
+
+	#[test]
+	fn test_unmake_move_restores_captures_and_rights() {
+		let mut game = GameState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 7 20").unwrap();
+		let m = Move::from_str("a1-a8").unwrap();
+		let undo = game.make_move(m);
+		assert_eq!(undo.captured, Some((Piece { side: Side::Black, piece_type: PieceType::Rook }, Board::coordinates_from_name("a8").unwrap())));
+		assert_eq!(undo.halfmove_clock, 7);
+		assert_eq!(game.halfmove_clock, 0);
+		game.unmake_move(m, undo);
+		assert_eq!(game.to_fen(), "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 7 20");
+	}
+
+	#[test]
+	fn test_undo_redo() {
+		let mut game = GameState::new();
+		assert_eq!(game.undo(), None);
+		play(&mut game, "e2-e4 d7-d5 e4-d5 d8-d5");
+		let after_four = game.to_fen();
+		assert_eq!(game.undo(), Some(Move::from_str("d8-d5").unwrap()));
+		assert_eq!(game.undo(), Some(Move::from_str("e4-d5").unwrap()));
+		assert_eq!(game.board.piece_at_square_name("d5").unwrap().side, Side::Black);
+		assert_eq!(game.side_to_move, Side::White);
+		assert_eq!(game.redo(), Some(Move::from_str("e4-d5").unwrap()));
+		assert_eq!(game.redo(), Some(Move::from_str("d8-d5").unwrap()));
+		assert_eq!(game.redo(), None);
+		assert_eq!(game.to_fen(), after_four);
+
+		while game.undo().is_some() {}
+		assert_same_state(&game, &GameState::new());
+		game.make_move(Move::from_str("d2-d4").unwrap());
+		assert_eq!(game.redo(), None);
+
+		// Trying a move out and taking it back keeps the moves waiting to be redone.
+		let mut game = GameState::new();
+		play(&mut game, "e2-e4 e7-e5");
+		game.undo();
+		let trial = Move::from_str("c7-c5").unwrap();
+		let undo = game.make_move(trial);
+		game.unmake_move(trial, undo);
+		assert_eq!(game.redo(), Some(Move::from_str("e7-e5").unwrap()));
+		// Playing a different move discards them, even after undoing it.
+		game.undo();
+		game.make_move(trial);
+		game.undo();
+		assert_eq!(game.redo(), Some(trial));
+		assert_eq!(game.redo(), None);
+	}
+
+	#[test]
+	fn test_hash() {
+		let hash = |fen: &str| GameState::from_fen(fen).unwrap().hash();
+		let mut game = GameState::new();
+		assert_eq!(game.hash(), hash("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"));
+		play(&mut game, "g1-f3 g8-f6 b1-c3 b8-c6");
+		let mut transposed = GameState::new();
+		play(&mut transposed, "b1-c3 b8-c6 g1-f3 g8-f6");
+		assert_eq!(game.hash(), transposed.hash());
+		assert_eq!(game.hash(), hash("r1bqkb1r/pppppppp/2n2n2/8/8/2N2N2/PPPPPPPP/R1BQKB1R w KQkq - 4 3"));
+
+		assert_ne!(hash("4k3/8/8/8/8/8/8/4K2R w K - 0 1"), hash("4k3/8/8/8/8/8/8/4K2R w - - 0 1"));
+		assert_ne!(hash("4k3/8/8/8/8/8/8/4K2R w - - 0 1"), hash("4k3/8/8/8/8/8/8/4K2R b - - 0 1"));
+		assert_ne!(hash("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1"), hash("4k3/8/8/8/4P3/8/8/4K3 b - - 0 1"));
+		assert_eq!(hash("4k3/8/8/8/8/8/8/4K3 w - - 0 1"), hash("4k3/8/8/8/8/8/8/4K3 w - - 31 90"));
+
+		let before = game.hash();
+		let undo = game.make_move(Move::from_str("e2-e4").unwrap());
+		assert_eq!(game.hash(), hash(&game.to_fen()));
+		game.unmake_move(Move::from_str("e2-e4").unwrap(), undo);
+		assert_eq!(game.hash(), before);
+	}
+
+	#[test]
+	fn test_outcome() {
+		let outcome = |fen: &str| GameState::from_fen(fen).unwrap().outcome();
+		assert_eq!(GameState::new().outcome(), None);
+		assert_eq!(outcome("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1"), Some(Outcome { winner: Some(Side::White), termination: Termination::Checkmate }));
+		assert_eq!(outcome("k7/2Q5/8/8/8/8/8/7K b - - 0 1"), Some(Outcome { winner: None, termination: Termination::Stalemate }));
+		assert_eq!(outcome("8/8/4k3/8/8/3K4/5N2/8 b - - 0 1"), Some(Outcome { winner: None, termination: Termination::InsufficientMaterial }));
+		assert_eq!(outcome("8/8/4k3/8/8/3K4/8/7R b - - 150 200"), Some(Outcome { winner: None, termination: Termination::SeventyFiveMoves }));
+		assert_eq!(outcome("8/8/4k3/8/8/3K4/8/7R b - - 120 200"), None);
+
+		let mut game = GameState::new();
+		play(&mut game, "f2-f3 e7-e5 g2-g4 d8-h4");
+		let mate = game.outcome().unwrap();
+		assert_eq!(mate.result(), "0-1");
+		assert_eq!(mate.to_string(), "0-1 (checkmate)");
+
+		let mut game = GameState::new();
+		for _ in 0..4 {
+			play(&mut game, "g1-f3 g8-f6 f3-g1 f6-g8");
+		}
+		assert_eq!(game.outcome().unwrap().termination, Termination::FivefoldRepetition);
+	}
+
+	#[test]
+	fn test_declared_outcomes() {
+		let mut game = GameState::new();
+		assert!(!game.claim_draw());
+		assert_eq!(game.outcome(), None);
+		play(&mut game, "g1-f3 g8-f6 f3-g1 f6-g8 g1-f3 g8-f6 f3-g1 f6-g8");
+		assert!(game.claim_draw());
+		assert_eq!(game.outcome(), Some(Outcome { winner: None, termination: Termination::ThreefoldRepetition }));
+		game.undo();
+		assert_eq!(game.outcome(), None);
+
+		game.resign(Side::White);
+		assert_eq!(game.outcome(), Some(Outcome { winner: Some(Side::Black), termination: Termination::Resignation }));
+		game.agree_draw();
+		assert_eq!(game.outcome().unwrap().result(), "1/2-1/2");
+
+		let mut game = GameState::from_fen("8/8/4k3/8/8/3K4/5N2/7q w - - 30 60").unwrap();
+		game.flag(Side::Black);
+		assert_eq!(game.outcome(), Some(Outcome { winner: None, termination: Termination::Timeout }));
+		game.flag(Side::White);
+		assert_eq!(game.outcome(), Some(Outcome { winner: Some(Side::Black), termination: Termination::Timeout }));
+
+		let mut game = GameState::from_fen("8/8/4k3/8/8/3K4/5N2/7q w - - 100 60").unwrap();
+		assert!(game.claim_draw());
+		assert_eq!(game.outcome().unwrap().termination, Termination::FiftyMoves);
+	}
+
+	#[test]
+	fn test_parse_move() {
+		assert_eq!(Move::from_str("e7-e8=q"), Ok(Move::new_with_promo((6, 4), (7, 4), PieceType::Queen)));
+		assert_eq!(Move::from_str("b2-a1=N"), Ok(Move::new_with_promo((1, 1), (0, 0), PieceType::Knight)));
+		let promotion = Move::new_with_promo((6, 0), (7, 1), PieceType::Rook);
+		assert_eq!(promotion.to_string().parse::<Move>(), Ok(promotion));
+		assert_eq!("h8".parse::<Square>(), Ok(Square::new(7, 7)));
+		assert_eq!(Square::new(2, 3).to_string(), "d3");
+
+		assert_eq!(Move::from_str(""), Err(ParseMoveError::Empty));
+		assert_eq!(Move::from_str("e2e4"), Err(ParseMoveError::MissingSeparator(String::from("e2e4"))));
+		assert_eq!(Move::from_str("z9-e4"), Err(ParseMoveError::InvalidSquare(String::from("z9"))));
+		assert_eq!(Move::from_str("e2-"), Err(ParseMoveError::InvalidSquare(String::from(""))));
+		assert_eq!(Move::from_str("e2-e4-e5"), Err(ParseMoveError::InvalidSquare(String::from("e4-e5"))));
+		assert_eq!(Move::from_str("e7-e8=k"), Err(ParseMoveError::InvalidPromotion(String::from("k"))));
+		assert_eq!(Move::from_str("e7-e8="), Err(ParseMoveError::InvalidPromotion(String::from(""))));
+		assert_eq!(Move::from_str("e7-e8=qq"), Err(ParseMoveError::InvalidPromotion(String::from("qq"))));
+		assert_eq!("".parse::<Square>(), Err(ParseMoveError::InvalidSquare(String::from(""))));
+		assert_eq!("a0".parse::<Square>(), Err(ParseMoveError::InvalidSquare(String::from("a0"))));
+		assert_eq!("A1".parse::<Square>(), Err(ParseMoveError::InvalidSquare(String::from("A1"))));
+		assert_eq!("a10".parse::<Square>(), Err(ParseMoveError::InvalidSquare(String::from("a10"))));
+	}
+
+	#[test]
+	fn test_parse_move_never_panics() {
+		use rand::prelude::*;
+		let mut rng = StdRng::seed_from_u64(8);
+		let alphabet: Vec<char> = "abcdefghz0123456789-=qrbnkpQRBNK xé\u{0}\u{1F600}".chars().collect();
+		for _ in 0..20000 {
+			let length = rng.gen_range(0..10);
+			let input: String = (0..length).map(|_| alphabet[rng.gen_range(0..alphabet.len())]).collect();
+			if let Ok(m) = input.parse::<Move>() {
+				assert!(Board::within_bounds(m.from) && Board::within_bounds(m.to));
+				assert_eq!(m.to_string().parse::<Move>(), Ok(m));
+			}
+			if let Ok(square) = input.parse::<Square>() {
+				assert_eq!(square.to_string(), input);
+			}
+		}
+	}
+
+	#[test]
+	fn test_move_to_san() {
+		let san = |fen: &str, m: &str| GameState::from_fen(fen).unwrap().move_to_san(Move::from_str(m).unwrap());
+		let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+		assert_eq!(san(start, "e2-e4"), "e4");
+		assert_eq!(san(start, "g1-f3"), "Nf3");
+		assert_eq!(san("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", "e4-d5"), "exd5");
+		assert_eq!(san("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", "e5-f6"), "exf6");
+		assert_eq!(san("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1-g1"), "O-O");
+		assert_eq!(san("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1", "e8-c8"), "O-O-O");
+		assert_eq!(san("r3k2r/8/8/8/8/8/8/R2K3R b kq - 0 1", "e8-c8"), "O-O-O+");
+		assert_eq!(san("8/4P3/8/7k/8/8/8/K7 w - - 0 1", "e7-e8=q"), "e8=Q+");
+		assert_eq!(san("8/4P3/8/7k/8/8/8/K7 w - - 0 1", "e7-e8=n"), "e8=N");
+		assert_eq!(san("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2", "d8-h4"), "Qh4#");
+		assert_eq!(san("6k1/8/8/8/8/8/8/R4RK1 w - - 0 1", "a1-d1"), "Rad1");
+		assert_eq!(san("6k1/8/8/8/8/4R3/8/4R1K1 w - - 0 1", "e1-e2"), "R1e2");
+		assert_eq!(san("6k1/8/8/8/8/4R3/8/4R1K1 w - - 0 1", "e3-e2"), "R3e2");
+		let queens = "8/2k5/8/8/4Q2Q/8/8/K6Q w - - 0 1";
+		assert_eq!(san(queens, "h4-e1"), "Qh4e1");
+		assert_eq!(san(queens, "e4-e1"), "Qee1");
+		assert_eq!(san(queens, "h1-e1"), "Q1e1");
+		// The knight on d2 is pinned, so only one knight can actually reach f3.
+		assert_eq!(san("4k3/8/8/8/8/8/3N4/4K1N1 w - - 0 1", "d2-f3"), "Ndf3");
+		assert_eq!(san("4k3/8/8/b7/8/8/3N4/4K1N1 w - - 0 1", "g1-f3"), "Nf3");
+	}
+
+	#[test]
+	fn test_parse_san() {
+		let mut game = GameState::new();
+		for san in ["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6", "O-O", "Be7", "Re1", "b5", "Bb3", "d6", "c3", "O-O", "h3", "Nb8", "d4", "Nbd7"] {
+			let m = game.parse_san(san).unwrap();
+			assert_eq!(game.move_to_san(m), san);
+			game.make_move(m);
+		}
+		assert_eq!(game.to_fen(), "r1bq1rk1/2pnbppp/p2p1n2/1p2p3/3PP3/1BP2N1P/PP3PP1/RNBQR1K1 w - - 1 11");
+
+		let game = GameState::from_fen("r3k2r/1P6/8/8/8/8/2P5/R3K2R w KQkq - 0 1").unwrap();
+		let parse = |san: &str| game.parse_san(san).map(|m| m.to_string());
+		assert_eq!(parse("0-0"), Ok(String::from("e1-g1")));
+		assert_eq!(parse("O-O-O+"), Ok(String::from("e1-c1")));
+		assert_eq!(parse("bxa8=Q+"), Ok(String::from("b7-a8=q")));
+		assert_eq!(parse("bxa8q"), Ok(String::from("b7-a8=q")));
+		assert_eq!(parse("b8=N"), Ok(String::from("b7-b8=n")));
+		assert_eq!(parse("rd1"), Ok(String::from("a1-d1")));
+		assert_eq!(parse("Ra1d1"), Ok(String::from("a1-d1")));
+		assert_eq!(parse("kd2"), Ok(String::from("e1-d2")));
+		assert_eq!(parse("c4!?"), Ok(String::from("c2-c4")));
+		assert_eq!(parse("Rxa8"), Ok(String::from("a1-a8")));
+		assert_eq!(parse("c2c4"), Ok(String::from("c2-c4")));
+		assert_eq!(parse("b8"), Err(SanError::NoMatchingMove(String::from("b8"))));
+		assert_eq!(parse("c5"), Err(SanError::NoMatchingMove(String::from("c5"))));
+		assert_eq!(parse("Zf1"), Err(SanError::Syntax(String::from("Zf1"))));
+		assert_eq!(parse("e9"), Err(SanError::Syntax(String::from("e9"))));
+		assert_eq!(parse("+"), Err(SanError::Empty));
+
+		let game = GameState::from_fen("4k3/8/8/B7/8/2p5/1P6/4K3 w - - 0 1").unwrap();
+		assert_eq!(game.parse_san("bxc3").unwrap().to_string(), "b2-c3");
+		assert_eq!(game.parse_san("Bxc3").unwrap().to_string(), "a5-c3");
+		let game = GameState::from_fen("4k3/8/8/B7/8/2p5/8/4K3 w - - 0 1").unwrap();
+		assert_eq!(game.parse_san("bxc3").unwrap().to_string(), "a5-c3");
+		let game = GameState::from_fen("8/2k5/8/8/4Q2Q/8/8/K6Q w - - 0 1").unwrap();
+		assert_eq!(game.parse_san("Qe1"), Err(SanError::Ambiguous(String::from("Qe1"))));
+		assert_eq!(game.parse_san("Qhe1"), Err(SanError::Ambiguous(String::from("Qhe1"))));
+		assert_eq!(game.parse_san("Qh4e1").unwrap().to_string(), "h4-e1");
+	}
+
+	#[test]
+	fn test_uci_moves() {
+		let mut game = GameState::new();
+		let e4 = Move::from_uci("e2e4", &game).unwrap();
+		assert_eq!(e4, Move::from_str("e2-e4").unwrap());
+		assert_eq!(e4.to_uci(), "e2e4");
+		assert_eq!(Move::from_uci("e2e5", &game), Err(ParseMoveError::IllegalMove(String::from("e2e5"))));
+		assert_eq!(Move::from_uci("e7e5", &game), Err(ParseMoveError::IllegalMove(String::from("e7e5"))));
+		game.make_move(e4);
+		assert_eq!(Move::from_uci("e7e5", &game).unwrap().to_uci(), "e7e5");
+
+		let game = GameState::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+		assert_eq!(Move::from_uci("e1g1", &game).unwrap().to_uci(), "e1g1");
+		assert_eq!(Move::from_uci("e1h1", &game).unwrap().to_uci(), "e1g1");
+		assert_eq!(Move::from_uci("e1a1", &game).unwrap().to_uci(), "e1c1");
+		assert_eq!(Move::from_uci("e1f1", &game).unwrap().to_uci(), "e1f1");
+		assert_eq!(Move::from_uci("b7a8n", &game).unwrap(), Move::new_with_promo((6, 1), (7, 0), PieceType::Knight));
+		assert_eq!(Move::from_uci("b7b8", &game).unwrap().to_uci(), "b7b8q");
+		assert_eq!(Move::from_uci("b7b8k", &game), Err(ParseMoveError::InvalidPromotion(String::from("k"))));
+		assert_eq!(Move::from_uci("b7b8qq", &game), Err(ParseMoveError::InvalidPromotion(String::from("qq"))));
+		assert_eq!(Move::from_uci("b7", &game), Err(ParseMoveError::InvalidSquare(String::from("b7"))));
+		assert_eq!(Move::from_uci("é7b8", &game), Err(ParseMoveError::InvalidSquare(String::from("é"))));
+		assert_eq!(Move::from_uci("", &game), Err(ParseMoveError::Empty));
+
+		let game = GameState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1").unwrap();
+		assert_eq!(Move::from_uci("e1h1", &game), Err(ParseMoveError::IllegalMove(String::from("e1h1"))));
+	}
+
+	#[test]
+	fn test_try_make_move() {
+		let check = |fen: &str, m: &str| GameState::from_fen(fen).unwrap().check_move(Move::from_str(m).unwrap());
+		let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+		assert_eq!(check(start, "e2-e4"), Ok(()));
+		assert_eq!(check(start, "e4-e5"), Err(IllegalMove::NoPieceOnOrigin));
+		assert_eq!(check(start, "e7-e5"), Err(IllegalMove::WrongSideToMove));
+		assert_eq!(check(start, "d1-d2"), Err(IllegalMove::CaptureOwnPiece));
+		assert_eq!(check(start, "g1-g3"), Err(IllegalMove::PieceCannotMoveThatWay));
+		assert_eq!(check(start, "e2-e5"), Err(IllegalMove::PieceCannotMoveThatWay));
+		assert_eq!(check(start, "e2-d3"), Err(IllegalMove::NothingToCapture));
+		assert_eq!(check(start, "a1-a3"), Err(IllegalMove::PathBlocked));
+		assert_eq!(check(start, "f1-c4"), Err(IllegalMove::PathBlocked));
+		assert_eq!(check("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2", "e4-e5"), Err(IllegalMove::PathBlocked));
+		assert_eq!(check("4k3/8/8/8/4p3/8/4P3/4K3 w - - 0 1", "e2-e4"), Err(IllegalMove::PathBlocked));
+		assert_eq!(check("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1", "e2-d3"), Err(IllegalMove::LeavesKingInCheck));
+		assert_eq!(check("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1", "e1-e2"), Err(IllegalMove::LeavesKingInCheck));
+		assert_eq!(check("k3r3/8/8/8/8/3n4/4P3/4K3 w - - 0 1", "e2-d3"), Err(IllegalMove::LeavesKingInCheck));
+		assert_eq!(check("8/8/8/K2pP2r/8/8/8/7k w - d6 0 1", "e5-d6"), Err(IllegalMove::LeavesKingInCheck));
+
+		let castling = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+		assert_eq!(check(castling, "e1-g1"), Ok(()));
+		assert_eq!(check("r3k2r/8/8/8/8/8/8/R3K2R w Qkq - 0 1", "e1-g1"), Err(IllegalMove::CastlingRightsLost));
+		assert_eq!(check("r3k2r/8/8/8/8/8/8/R2NK2R w KQkq - 0 1", "e1-c1"), Err(IllegalMove::PathBlocked));
+		assert_eq!(check("r3k2r/8/8/8/8/8/8/RN2K2R w KQkq - 0 1", "e1-c1"), Err(IllegalMove::PathBlocked));
+		assert_eq!(check("r3k2r/4r3/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1-g1"), Err(IllegalMove::CastlingOutOfCheck));
+		assert_eq!(check("r3k2r/5r2/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1-g1"), Err(IllegalMove::CastlingThroughCheck));
+		assert_eq!(check("r3k2r/6r1/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1-g1"), Err(IllegalMove::LeavesKingInCheck));
+		assert_eq!(check("r3k2r/1r6/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1-c1"), Ok(()));
+		assert_eq!(check("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1-e3"), Err(IllegalMove::PieceCannotMoveThatWay));
+
+		let promotion = "2n1k3/1P6/8/8/8/8/8/4K3 w - - 0 1";
+		assert_eq!(check(promotion, "b7-b8=q"), Ok(()));
+		assert_eq!(check(promotion, "b7-c8=n"), Ok(()));
+		assert_eq!(check(promotion, "b7-b8"), Err(IllegalMove::MissingPromotion));
+		assert_eq!(check(promotion, "b7-a8"), Err(IllegalMove::NothingToCapture));
+		assert_eq!(GameState::from_fen(promotion).unwrap().check_move(Move::new_with_promo((6, 1), (7, 1), PieceType::King)), Err(IllegalMove::InvalidPromotion));
+		assert_eq!(check(promotion, "e1-e2=q"), Err(IllegalMove::InvalidPromotion));
+		assert_eq!(check("4k3/8/8/8/8/8/1P6/4K3 w - - 0 1", "b2-b3=q"), Err(IllegalMove::InvalidPromotion));
+
+		let mut game = GameState::new();
+		assert_eq!(game.try_make_move(Move::from_str("e2-e5").unwrap()), Err(IllegalMove::PieceCannotMoveThatWay));
+		assert_eq!(game.side_to_move, Side::White);
+		assert_eq!(game.try_make_move(Move::from_str("e2-e4").unwrap()), Ok(()));
+		assert_eq!(game.side_to_move, Side::Black);
+		assert_eq!(game.try_make_move(Move { from: (8, 0), to: (7, 0), promo: None }), Err(IllegalMove::OffBoard));
+	}
+
+	#[test]
+	fn test_move_is_legal_agrees_with_legal_moves() {
+		use rand::prelude::*;
+		let mut rng = StdRng::seed_from_u64(15);
+		for _ in 0..3 {
+			let mut game = GameState::new();
+			while game.outcome().is_none() && game.fullmove_number < 30 {
+				let legal_moves = game.get_legal_moves();
+				for from_rank in 0..8 {
+					for from_file in 0..8 {
+						if game.board.piece_at((from_rank, from_file)).is_none() {
+							continue;
+						}
+						for to_rank in 0..8 {
+							for to_file in 0..8 {
+								let m = Move::new((from_rank, from_file), (to_rank, to_file));
+								assert_eq!(game.move_is_legal(m), legal_moves.contains(&m), "{} in {}", m, game.to_fen());
+							}
+						}
+					}
+				}
+				game.make_move(legal_moves[rng.gen_range(0..legal_moves.len())]);
+			}
+		}
+	}
+
+	/// The copy-and-test generator that `get_legal_moves` replaced: each pseudo-legal move is played
+	/// on a copy to see whether it leaves the king in check, and castling is checked square by square.
+	fn legal_moves_by_copy(game: &GameState) -> Vec<Move> {
+		let mut moves: Vec<Move> = game.get_possible_moves().into_iter().filter(|m| !game.move_would_put_self_in_check(*m)).collect();
+		let (home_rank, kingside, queenside) = match game.side_to_move {
+			Side::White => (0, game.castling_availability.white_can_castle_kingside, game.castling_availability.white_can_castle_queenside),
+			Side::Black => (7, game.castling_availability.black_can_castle_kingside, game.castling_availability.black_can_castle_queenside),
+		};
+		let king = (home_rank, 4);
+		if game.is_in_check(game.side_to_move) || game.board.piece_at(king) != Some(Piece::new(game.side_to_move, PieceType::King)) {
+			return moves;
+		}
+		for (has_right, path, passed_over) in [(kingside, vec![5, 6], [5, 6]), (queenside, vec![1, 2, 3], [3, 2])] {
+			if has_right && path.iter().all(|file| game.board.piece_at((home_rank, *file)).is_none()) &&
+				passed_over.iter().all(|file| !game.move_would_put_self_in_check(Move::new(king, (home_rank, *file)))) {
+				moves.push(Move::new(king, (home_rank, passed_over[1])));
+			}
+		}
+		moves
+	}
+
+	#[test]
+	fn test_legal_moves_match_copy_and_test() {
+		use rand::prelude::*;
+		let sorted = |moves: Vec<Move>| {
+			let mut names: Vec<String> = moves.iter().map(Move::to_uci).collect();
+			names.sort();
+			names
+		};
+		let mut rng = StdRng::seed_from_u64(20);
+		let mut positions = 0;
+		for fen in [
+			"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+			"r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+			"8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+			"r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+			"rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+			"8/8/8/KPp4r/8/8/8/7k w - c6 0 1",
+			"4k3/8/8/8/1b6/8/3P4/4K3 w - - 0 1",
+		] {
+			for _ in 0..15 {
+				let mut game = GameState::from_fen(fen).unwrap();
+				for _ in 0..80 {
+					let legal_moves = game.get_legal_moves();
+					assert_eq!(sorted(legal_moves.clone()), sorted(legal_moves_by_copy(&game)), "{}", game.to_fen());
+					positions += 1;
+					if legal_moves.is_empty() {
+						break;
+					}
+					game.make_move(legal_moves[rng.gen_range(0..legal_moves.len())]);
+				}
+			}
+		}
+		assert!(positions > 5000);
+
+		let horizontal_pin = GameState::from_fen("8/8/8/KPp4r/8/8/8/7k w - c6 0 1").unwrap();
+		assert!(!horizontal_pin.get_legal_moves().contains(&Move::from_str("b5-c6").unwrap()));
+		let checked_by_double_step = GameState::from_fen("8/8/8/2pP4/1K6/8/8/7k w - c6 0 1").unwrap();
+		assert!(checked_by_double_step.get_legal_moves().contains(&Move::from_str("d5-c6").unwrap()));
+	}
+}
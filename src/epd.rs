@@ -0,0 +1,358 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+use crate::chess::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EpdError {
+	Fen(FenError),
+	Syntax(String),
+	InvalidClock { opcode: String, operand: String },
+	InvalidMove { opcode: String, san: String, error: SanError },
+}
+
+impl fmt::Display for EpdError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			EpdError::Fen(error) => write!(f, "{}", error),
+			EpdError::Syntax(message) => write!(f, "{}", message),
+			EpdError::InvalidClock { opcode, operand } => write!(f, "{}: '{}' is not a valid count", opcode, operand),
+			EpdError::InvalidMove { opcode, san, error } => write!(f, "{}: {} ('{}')", opcode, error, san),
+		}
+	}
+}
+
+impl std::error::Error for EpdError {}
+
+impl From<FenError> for EpdError {
+	fn from(error: FenError) -> EpdError {
+		EpdError::Fen(error)
+	}
+}
+
+/// One line of an EPD file: a position given by the first four FEN fields, followed by
+/// operations such as `bm Qg6;` or `id "WAC.001";`.
+#[derive(Clone)]
+pub struct EpdRecord {
+	pub game: GameState,
+	pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl EpdRecord {
+	pub fn new(game: GameState) -> EpdRecord {
+		EpdRecord { game, operations: Vec::new() }
+	}
+
+	/// Parses a line of EPD. The `hmvc` and `fmvn` operations, if present, set the position's
+	/// halfmove clock and fullmove number.
+	pub fn parse(line: &str) -> Result<EpdRecord, EpdError> {
+		let line = line.trim();
+		let mut fields = Vec::new();
+		let mut rest = line;
+		for _ in 0..4 {
+			rest = rest.trim_start();
+			let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+			fields.push(&rest[..end]);
+			rest = &rest[end..];
+		}
+		let mut game = GameState::from_fen(&fields.join(" "))?;
+		let operations = parse_operations(rest)?;
+
+		for (opcode, operands) in &operations {
+			if opcode == "hmvc" || opcode == "fmvn" {
+				let invalid = || EpdError::InvalidClock { opcode: opcode.clone(), operand: operands.join(" ") };
+				let value: u32 = match operands.as_slice() {
+					[operand] => operand.parse().map_err(|_| invalid())?,
+					_ => return Err(invalid()),
+				};
+				if opcode == "hmvc" {
+					game.halfmove_clock = value;
+				} else if value > 0 {
+					game.fullmove_number = value;
+				} else {
+					return Err(invalid());
+				}
+			}
+		}
+		let record = EpdRecord { game, operations };
+		record.best_moves()?;
+		record.avoid_moves()?;
+		Ok(record)
+	}
+
+	pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+		self.operations.iter().find(|(name, _)| name == opcode).map(|(_, operands)| operands.as_slice())
+	}
+
+	/// Sets an operation, replacing any earlier one with the same opcode.
+	pub fn set_operation(&mut self, opcode: &str, operands: Vec<String>) {
+		match self.operations.iter_mut().find(|(name, _)| name == opcode) {
+			Some((_, old)) => *old = operands,
+			None => self.operations.push((String::from(opcode), operands)),
+		}
+	}
+
+	pub fn id(&self) -> Option<&str> {
+		self.operation("id").and_then(|operands| operands.first()).map(|id| id.as_str())
+	}
+
+	/// The moves listed by the `bm` operation, empty if there is none.
+	pub fn best_moves(&self) -> Result<Vec<Move>, EpdError> {
+		self.moves_for("bm")
+	}
+
+	/// The moves listed by the `am` operation, empty if there is none.
+	pub fn avoid_moves(&self) -> Result<Vec<Move>, EpdError> {
+		self.moves_for("am")
+	}
+
+	fn moves_for(&self, opcode: &str) -> Result<Vec<Move>, EpdError> {
+		self.operation(opcode).unwrap_or(&[]).iter()
+			.map(|san| self.game.parse_san(san).map_err(|error| EpdError::InvalidMove { opcode: String::from(opcode), san: san.clone(), error }))
+			.collect()
+	}
+
+	/// Writes the record back out as a line of EPD.
+	pub fn to_epd(&self) -> String {
+		let fen = self.game.to_fen();
+		let mut epd = fen.split(' ').take(4).collect::<Vec<&str>>().join(" ");
+		for (opcode, operands) in &self.operations {
+			epd.push(' ');
+			epd.push_str(opcode);
+			for operand in operands {
+				epd.push(' ');
+				if is_string_opcode(opcode) || operand.is_empty() || operand.contains(|c: char| c.is_whitespace() || c == ';' || c == '"') {
+					epd.push_str(&format!("\"{}\"", operand.replace('\\', "\\\\").replace('"', "\\\"")));
+				} else {
+					epd.push_str(operand);
+				}
+			}
+			epd.push(';');
+		}
+		epd
+	}
+}
+
+impl fmt::Display for EpdRecord {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.to_epd())
+	}
+}
+
+/// Opcodes whose operands are always written as quoted strings.
+fn is_string_opcode(opcode: &str) -> bool {
+	opcode == "id" || (opcode.len() == 2 && opcode.starts_with('c') && opcode.as_bytes()[1].is_ascii_digit())
+}
+
+fn parse_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, EpdError> {
+	let mut operations = Vec::new();
+	let mut chars = text.chars().peekable();
+	loop {
+		while chars.peek().is_some_and(|c| c.is_whitespace()) {
+			chars.next();
+		}
+		if chars.peek().is_none() {
+			return Ok(operations);
+		}
+		let mut opcode = String::new();
+		while let Some(c) = chars.peek().filter(|c| c.is_ascii_alphanumeric() || **c == '_') {
+			opcode.push(*c);
+			chars.next();
+		}
+		if opcode.is_empty() {
+			return Err(EpdError::Syntax(format!("expected an opcode, found '{}'", chars.collect::<String>())));
+		}
+		let mut operands = Vec::new();
+		loop {
+			match chars.next() {
+				None => return Err(EpdError::Syntax(format!("operation '{}' is missing its ';'", opcode))),
+				Some(';') => break,
+				Some(c) if c.is_whitespace() => (),
+				Some('"') => {
+					let mut operand = String::new();
+					loop {
+						match chars.next() {
+							None => return Err(EpdError::Syntax(format!("unterminated string in operation '{}'", opcode))),
+							Some('"') => break,
+							Some('\\') => operand.extend(chars.next()),
+							Some(c) => operand.push(c),
+						}
+					}
+					operands.push(operand);
+				},
+				Some(c) => {
+					let mut operand = String::from(c);
+					while let Some(c) = chars.peek().filter(|c| !c.is_whitespace() && **c != ';') {
+						operand.push(*c);
+						chars.next();
+					}
+					operands.push(operand);
+				},
+			}
+		}
+		operations.push((opcode, operands));
+	}
+}
+
+/// Reads every non-blank line of an EPD file. Errors carry the 1-based line number.
+pub fn parse_suite(text: &str) -> Result<Vec<EpdRecord>, (usize, EpdError)> {
+	text.lines().enumerate()
+		.filter(|(_, line)| !line.trim().is_empty())
+		.map(|(i, line)| EpdRecord::parse(line).map_err(|error| (i + 1, error)))
+		.collect()
+}
+
+/// How the move picker did on one position of a suite.
+#[derive(Clone, Debug)]
+pub struct PositionResult {
+	pub id: Option<String>,
+	pub best_moves: Vec<Move>,
+	pub avoid_moves: Vec<Move>,
+	pub played: Option<Move>,
+	pub time: Duration,
+}
+
+impl PositionResult {
+	pub fn matched_best_move(&self) -> bool {
+		self.played.is_some_and(|m| self.best_moves.contains(&m))
+	}
+
+	pub fn played_avoid_move(&self) -> bool {
+		self.played.is_some_and(|m| self.avoid_moves.contains(&m))
+	}
+
+	/// A position is solved when the picker chose a move, it was one of the best moves (if any were
+	/// given) and it wasn't one of the moves to avoid.
+	pub fn solved(&self) -> bool {
+		self.played.is_some() && (self.best_moves.is_empty() || self.matched_best_move()) && !self.played_avoid_move()
+	}
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SuiteReport {
+	pub results: Vec<PositionResult>,
+}
+
+impl SuiteReport {
+	pub fn best_moves_matched(&self) -> usize {
+		self.results.iter().filter(|result| result.matched_best_move()).count()
+	}
+
+	pub fn avoid_moves_played(&self) -> usize {
+		self.results.iter().filter(|result| result.played_avoid_move()).count()
+	}
+
+	pub fn solved(&self) -> usize {
+		self.results.iter().filter(|result| result.solved()).count()
+	}
+
+	pub fn total_time(&self) -> Duration {
+		self.results.iter().map(|result| result.time).sum()
+	}
+}
+
+impl fmt::Display for SuiteReport {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (i, result) in self.results.iter().enumerate() {
+			let verdict = if result.solved() { "ok" } else { "FAIL" };
+			let played = result.played.map(|m| m.to_uci()).unwrap_or_else(|| String::from("(none)"));
+			let id = result.id.clone().unwrap_or_else(|| format!("#{}", i + 1));
+			writeln!(f, "{:<4} {:<20} {:<6} {:>8.3}s", verdict, id, played, result.time.as_secs_f64())?;
+		}
+		write!(f, "solved {}/{}, best moves matched {}, avoid moves played {}, {:.3}s",
+			self.solved(), self.results.len(), self.best_moves_matched(), self.avoid_moves_played(), self.total_time().as_secs_f64())
+	}
+}
+
+/// Runs a move picker, such as `ai::next_move`, over every position of a suite and scores its
+/// choices against the `bm` and `am` operations.
+pub fn run_suite<F: FnMut(&GameState) -> Option<Move>>(records: &[EpdRecord], mut pick_move: F) -> SuiteReport {
+	let mut report = SuiteReport::default();
+	for record in records {
+		let started = Instant::now();
+		let played = pick_move(&record.game);
+		let time = started.elapsed();
+		report.results.push(PositionResult {
+			id: record.id().map(String::from),
+			best_moves: record.best_moves().unwrap_or_default(),
+			avoid_moves: record.avoid_moves().unwrap_or_default(),
+			played,
+			time,
+		});
+	}
+	report
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ai;
+
+	const SUITE: &str = "\
+2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";
+8/7p/5k2/5p2/p1p2P2/Pr1pPK2/1P1R3P/8 b - - bm Rxb2; id \"WAC.002\";
+
+7k/8/8/8/8/8/8/R3K3 w Q - am Kd1 Kd2; bm Ra8+; id \"mixed; case\"; c0 \"a comment\";
+";
+
+	#[test]
+	fn test_parse_epd() {
+		let records = parse_suite(SUITE).unwrap();
+		assert_eq!(records.len(), 3);
+		assert_eq!(records[0].id(), Some("WAC.001"));
+		assert_eq!(records[0].game.side_to_move, Side::White);
+		assert_eq!(records[0].best_moves().unwrap(), vec!["g3-g6".parse::<Move>().unwrap()]);
+		assert_eq!(records[1].best_moves().unwrap(), vec!["b3-b2".parse::<Move>().unwrap()]);
+		assert_eq!(records[2].id(), Some("mixed; case"));
+		assert_eq!(records[2].avoid_moves().unwrap().len(), 2);
+		assert_eq!(records[2].operation("c0"), Some(&[String::from("a comment")][..]));
+		assert_eq!(records[2].operation("dm"), None);
+
+		let record = EpdRecord::parse("4k3/8/8/8/8/8/8/4K2R w K - hmvc 12; fmvn 40; bm O-O;").unwrap();
+		assert_eq!(record.game.to_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 12 40");
+		assert_eq!(record.best_moves().unwrap(), vec!["e1-g1".parse::<Move>().unwrap()]);
+	}
+
+	#[test]
+	fn test_parse_epd_errors() {
+		assert!(matches!(EpdRecord::parse("4k3/8/8/8/8/8/8/4K2R w K"), Err(EpdError::Fen(FenError::MissingField(FenField::EnPassant)))));
+		assert!(matches!(EpdRecord::parse("4k3/8/8/8/8/8/8/4K2R w K - bm O-O"), Err(EpdError::Syntax(_))));
+		assert!(matches!(EpdRecord::parse("4k3/8/8/8/8/8/8/4K2R w K - id \"open;"), Err(EpdError::Syntax(_))));
+		assert!(matches!(EpdRecord::parse("4k3/8/8/8/8/8/8/4K2R w K - ; bm O-O;"), Err(EpdError::Syntax(_))));
+		assert!(matches!(EpdRecord::parse("4k3/8/8/8/8/8/8/4K2R w K - hmvc x;"), Err(EpdError::InvalidClock { .. })));
+		assert!(matches!(EpdRecord::parse("4k3/8/8/8/8/8/8/4K2R w K - fmvn 0;"), Err(EpdError::InvalidClock { .. })));
+		assert!(matches!(EpdRecord::parse("4k3/8/8/8/8/8/8/4K2R w K - bm O-O-O;"), Err(EpdError::InvalidMove { .. })));
+		assert!(matches!(parse_suite("4k3/8/8/8/8/8/8/4K2R w K - bm O-O;\n\nbad"), Err((3, EpdError::Fen(_)))));
+	}
+
+	#[test]
+	fn test_write_epd() {
+		for line in SUITE.lines().filter(|line| !line.is_empty()) {
+			assert_eq!(EpdRecord::parse(line).unwrap().to_epd(), line);
+		}
+		let mut record = EpdRecord::new(GameState::new());
+		record.set_operation("id", vec![String::from("start")]);
+		record.set_operation("bm", vec![String::from("e4"), String::from("d4")]);
+		record.set_operation("c1", vec![String::from("say \"hi\"")]);
+		let epd = record.to_epd();
+		assert_eq!(epd, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - id \"start\"; bm e4 d4; c1 \"say \\\"hi\\\"\";");
+		assert_eq!(EpdRecord::parse(&epd).unwrap().operation("c1"), Some(&[String::from("say \"hi\"")][..]));
+	}
+
+	#[test]
+	fn test_run_suite() {
+		let records = parse_suite(SUITE).unwrap();
+		let mut answers = vec!["Kd1", "Rxb2", "Qg6"];
+		let report = run_suite(&records, |game| Some(game.parse_san(answers.pop().unwrap()).unwrap()));
+		assert_eq!(report.results.len(), 3);
+		assert_eq!(report.best_moves_matched(), 2);
+		assert_eq!(report.avoid_moves_played(), 1);
+		assert_eq!(report.solved(), 2);
+		assert!(report.results[1].solved());
+		assert!(!report.results[2].solved());
+
+		let report = run_suite(&records, ai::next_move);
+		assert_eq!(report.results.len(), 3);
+		assert!(report.results.iter().all(|result| result.played.is_some()));
+		assert!(report.to_string().contains("WAC.001"));
+	}
+}
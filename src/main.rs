@@ -1,9 +1,32 @@
 pub mod chess;
 pub mod ai;
+pub mod pgn;
+pub mod epd;
+pub mod perft;
+pub mod zobrist;
+pub mod bitboard;
+pub mod attacks;
+pub mod transposition;
 
 use chess::*;
 
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
 fn main() {
+	// `hexchess perft <depth> [fen]` times the move generator.
+	let args: Vec<String> = std::env::args().collect();
+	if let [_, command, depth, fen @ ..] = args.as_slice() {
+		if command == "perft" {
+			let fen = if fen.is_empty() { String::from(START_FEN) } else { fen.join(" ") };
+			match (depth.parse(), GameState::from_fen(&fen)) {
+				(Ok(depth), Ok(game)) => println!("{}", game.perft_speed(depth)),
+				(Err(_), _) => eprintln!("'{}' is not a depth", depth),
+				(_, Err(error)) => eprintln!("{}", error),
+			}
+			return;
+		}
+	}
+
 	let game = GameState::new();
     print!("{}", game.board.to_fen_grid());
 	let square_name = "f7";
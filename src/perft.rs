@@ -0,0 +1,221 @@
+use std::fmt;
+use std::ops::AddAssign;
+use std::time::{Duration, Instant};
+use crate::chess::*;
+
+/// Leaf statistics from a perft run, in the layout used by the published reference tables.
+/// Captures include en passant captures, and checks include checkmates.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PerftCounts {
+	pub nodes: u64,
+	pub captures: u64,
+	pub en_passant: u64,
+	pub castles: u64,
+	pub promotions: u64,
+	pub checks: u64,
+	pub checkmates: u64,
+}
+
+impl AddAssign for PerftCounts {
+	fn add_assign(&mut self, other: PerftCounts) {
+		self.nodes += other.nodes;
+		self.captures += other.captures;
+		self.en_passant += other.en_passant;
+		self.castles += other.castles;
+		self.promotions += other.promotions;
+		self.checks += other.checks;
+		self.checkmates += other.checkmates;
+	}
+}
+
+/// How long a perft run took, for comparing the speed of move generator changes.
+#[derive(Clone, Copy, Debug)]
+pub struct PerftSpeed {
+	pub nodes: u64,
+	pub elapsed: Duration,
+}
+
+impl PerftSpeed {
+	pub fn nodes_per_second(&self) -> f64 {
+		self.nodes as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+	}
+}
+
+impl fmt::Display for PerftSpeed {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} nodes in {:.3}s ({:.0} nodes/s)", self.nodes, self.elapsed.as_secs_f64(), self.nodes_per_second())
+	}
+}
+
+impl GameState {
+	/// Counts the leaf nodes of the legal move tree `depth` plies deep.
+	pub fn perft(&self, depth: u32) -> u64 {
+		let mut game = self.clone();
+		perft_nodes(&mut game, depth)
+	}
+
+	/// Perft, timed.
+	pub fn perft_speed(&self, depth: u32) -> PerftSpeed {
+		let start = Instant::now();
+		let nodes = self.perft(depth);
+		PerftSpeed { nodes, elapsed: start.elapsed() }
+	}
+
+	/// Perft split by root move, for tracking down which move leads to a wrong count.
+	pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+		let mut game = self.clone();
+		let mut results = Vec::new();
+		if depth == 0 {
+			return results;
+		}
+		for m in game.get_legal_moves() {
+			let undo = game.make_move(m);
+			results.push((m, perft_nodes(&mut game, depth - 1)));
+			game.unmake_move(m, undo);
+		}
+		results
+	}
+
+	/// Perft with counts of how each leaf was reached.
+	pub fn perft_counts(&self, depth: u32) -> PerftCounts {
+		let mut game = self.clone();
+		let mut counts = PerftCounts::default();
+		if depth == 0 {
+			counts.nodes = 1;
+		} else {
+			perft_counts(&mut game, depth, &mut counts);
+		}
+		counts
+	}
+}
+
+fn perft_nodes(game: &mut GameState, depth: u32) -> u64 {
+	if depth == 0 {
+		return 1;
+	}
+	let moves = game.get_legal_moves();
+	if depth == 1 {
+		return moves.len() as u64;
+	}
+	let mut nodes = 0;
+	for m in moves {
+		let undo = game.make_move(m);
+		nodes += perft_nodes(game, depth - 1);
+		game.unmake_move(m, undo);
+	}
+	nodes
+}
+
+fn perft_counts(game: &mut GameState, depth: u32, counts: &mut PerftCounts) {
+	for m in game.get_legal_moves() {
+		let piece_type = game.board.piece_at(m.from).unwrap().piece_type();
+		let is_pawn = piece_type == PieceType::Pawn;
+		let is_castle = piece_type == PieceType::King && (m.to.1 - m.from.1).abs() == 2;
+		let undo = game.make_move(m);
+		if depth == 1 {
+			counts.nodes += 1;
+			if let Some((_, captured_square)) = undo.captured {
+				counts.captures += 1;
+				if is_pawn && captured_square != m.to {
+					counts.en_passant += 1;
+				}
+			}
+			if is_castle {
+				counts.castles += 1;
+			}
+			if m.promo.is_some() {
+				counts.promotions += 1;
+			}
+			if game.is_in_check(game.side_to_move) {
+				counts.checks += 1;
+				if game.get_legal_moves().is_empty() {
+					counts.checkmates += 1;
+				}
+			}
+		} else {
+			perft_counts(game, depth - 1, counts);
+		}
+		game.unmake_move(m, undo);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+	const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+	const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+	const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+	const POSITION_4_MIRRORED: &str = "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+	const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+	const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+	fn counts(nodes: u64, captures: u64, en_passant: u64, castles: u64, promotions: u64, checks: u64, checkmates: u64) -> PerftCounts {
+		PerftCounts { nodes, captures, en_passant, castles, promotions, checks, checkmates }
+	}
+
+	#[test]
+	fn test_perft_counts() {
+		let table = [
+			(START, 1, counts(20, 0, 0, 0, 0, 0, 0)),
+			(START, 2, counts(400, 0, 0, 0, 0, 0, 0)),
+			(START, 3, counts(8902, 34, 0, 0, 0, 12, 0)),
+			(KIWIPETE, 1, counts(48, 8, 0, 2, 0, 0, 0)),
+			(KIWIPETE, 2, counts(2039, 351, 1, 91, 0, 3, 0)),
+			(POSITION_3, 1, counts(14, 1, 0, 0, 0, 2, 0)),
+			(POSITION_3, 2, counts(191, 14, 0, 0, 0, 10, 0)),
+			(POSITION_3, 3, counts(2812, 209, 2, 0, 0, 267, 0)),
+			(POSITION_4, 1, counts(6, 0, 0, 0, 0, 0, 0)),
+			(POSITION_4, 2, counts(264, 87, 0, 6, 48, 10, 0)),
+			(POSITION_4, 3, counts(9467, 1021, 4, 0, 120, 38, 22)),
+			(POSITION_4_MIRRORED, 3, counts(9467, 1021, 4, 0, 120, 38, 22)),
+		];
+		for (fen, depth, expected) in table {
+			assert_eq!(GameState::from_fen(fen).unwrap().perft_counts(depth), expected, "{} at depth {}", fen, depth);
+		}
+	}
+
+	#[test]
+	fn test_perft_nodes() {
+		let table = [
+			(START, 4, 197281),
+			(KIWIPETE, 3, 97862),
+			(POSITION_3, 4, 43238),
+			(POSITION_4, 3, 9467),
+			(POSITION_5, 1, 44),
+			(POSITION_5, 2, 1486),
+			(POSITION_5, 3, 62379),
+			(POSITION_6, 1, 46),
+			(POSITION_6, 2, 2079),
+			(POSITION_6, 3, 89890),
+		];
+		for (fen, depth, expected) in table {
+			assert_eq!(GameState::from_fen(fen).unwrap().perft(depth), expected, "{} at depth {}", fen, depth);
+		}
+	}
+
+	#[test]
+	fn test_divide() {
+		let game = GameState::from_fen(KIWIPETE).unwrap();
+		let divided = game.divide(2);
+		assert_eq!(divided.len(), 48);
+		assert_eq!(divided.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+		let castle = divided.iter().find(|(m, _)| m.to_uci() == "e1g1").unwrap();
+		assert_eq!(castle.1, 43);
+		assert_eq!(game.perft(0), 1);
+		assert!(game.divide(0).is_empty());
+	}
+
+	#[test]
+	fn test_castling_rights_lost_when_rook_is_captured() {
+		let mut game = GameState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+		game.make_move(game.parse_san("Rxa8+").unwrap());
+		assert_eq!(game.castling_availability.to_string(), "Kk");
+		game.make_move(game.parse_san("Ke7").unwrap());
+		game.make_move(game.parse_san("Ra1").unwrap());
+		game.make_move(game.parse_san("Rxh1+").unwrap());
+		assert_eq!(game.castling_availability.to_string(), "-");
+		assert_eq!(GameState::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap().perft(3), 13744);
+	}
+}
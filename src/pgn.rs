@@ -0,0 +1,749 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use crate::chess::*;
+
+const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+const MAX_LINE_LENGTH: usize = 80;
+
+/// A game together with the tags describing it. Moves played through the record are kept so the
+/// game can be written out as PGN.
+#[derive(Clone)]
+pub struct GameRecord {
+	tags: Vec<(String, String)>,
+	start: GameState,
+	game: GameState,
+	moves: Vec<Move>,
+}
+
+impl GameRecord {
+	pub fn new() -> GameRecord {
+		GameRecord::from_position(GameState::new())
+	}
+
+	/// Starts a record from a custom position, which is written out with `[SetUp]` and `[FEN]` tags.
+	pub fn from_position(start: GameState) -> GameRecord {
+		let tags = SEVEN_TAG_ROSTER.iter().map(|name| {
+			let value = match *name {
+				"Date" => "????.??.??",
+				"Result" => "*",
+				_ => "?",
+			};
+			(String::from(*name), String::from(value))
+		}).collect();
+		GameRecord {
+			tags,
+			game: start.clone(),
+			start,
+			moves: Vec::new(),
+		}
+	}
+
+	pub fn tag(&self, name: &str) -> Option<&str> {
+		self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str())
+	}
+
+	/// Sets a tag, replacing any earlier value. The Result tag is always written from the game's
+	/// outcome, so setting it only matters while the game is unfinished.
+	pub fn set_tag(&mut self, name: &str, value: &str) {
+		match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+			Some((_, old)) => *old = String::from(value),
+			None => self.tags.push((String::from(name), String::from(value))),
+		}
+	}
+
+	pub fn start(&self) -> &GameState {
+		&self.start
+	}
+
+	pub fn game(&self) -> &GameState {
+		&self.game
+	}
+
+	pub fn moves(&self) -> &[Move] {
+		&self.moves
+	}
+
+	/// Plays a move on the game and records it.
+	pub fn make_move(&mut self, m: Move) -> MoveUndo {
+		self.moves.push(m);
+		self.game.make_move(m)
+	}
+
+	pub fn resign(&mut self, side: Side) {
+		self.game.resign(side);
+	}
+
+	pub fn agree_draw(&mut self) {
+		self.game.agree_draw();
+	}
+
+	pub fn flag(&mut self, side: Side) {
+		self.game.flag(side);
+	}
+
+	pub fn claim_draw(&mut self) -> bool {
+		self.game.claim_draw()
+	}
+
+	/// The result token: the outcome if the game is over, otherwise the Result tag ("*" unless set).
+	pub fn result(&self) -> String {
+		match self.game.outcome() {
+			Some(outcome) => String::from(outcome.result()),
+			None => String::from(self.tag("Result").unwrap_or("*")),
+		}
+	}
+
+	/// Writes the game in PGN export format: the Seven Tag Roster first, then any other tags, then
+	/// the movetext in SAN wrapped to 80 columns and ending with the result.
+	pub fn to_pgn(&self) -> String {
+		let result = self.result();
+		let mut pgn = String::new();
+		for (name, value) in &self.tags {
+			let value = if name == "Result" { result.as_str() } else { value.as_str() };
+			pgn.push_str(&format!("[{} \"{}\"]\n", name, escape_tag_value(value)));
+			if name == "Result" {
+				let fen = self.start.to_fen();
+				if fen != STARTING_FEN && self.tag("FEN").is_none() {
+					pgn.push_str("[SetUp \"1\"]\n");
+					pgn.push_str(&format!("[FEN \"{}\"]\n", fen));
+				}
+			}
+		}
+		pgn.push('\n');
+
+		let mut tokens = Vec::new();
+		let mut replay = self.start.clone();
+		for (i, m) in self.moves.iter().enumerate() {
+			if replay.side_to_move == Side::White {
+				tokens.push(format!("{}.", replay.fullmove_number));
+			} else if i == 0 {
+				tokens.push(format!("{}...", replay.fullmove_number));
+			}
+			tokens.push(replay.move_to_san(*m));
+			replay.make_move(*m);
+		}
+		tokens.push(result);
+
+		let mut line = String::new();
+		for token in tokens {
+			if !line.is_empty() && line.len() + 1 + token.len() > MAX_LINE_LENGTH {
+				pgn.push_str(&line);
+				pgn.push('\n');
+				line.clear();
+			}
+			if !line.is_empty() {
+				line.push(' ');
+			}
+			line.push_str(&token);
+		}
+		pgn.push_str(&line);
+		pgn.push('\n');
+		pgn
+	}
+}
+
+impl Default for GameRecord {
+	fn default() -> GameRecord {
+		GameRecord::new()
+	}
+}
+
+impl fmt::Display for GameRecord {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}", self.to_pgn())
+	}
+}
+
+fn escape_tag_value(value: &str) -> String {
+	value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[derive(Debug)]
+pub enum PgnError {
+	Io(io::Error),
+	Syntax { game: usize, line: usize, message: String },
+	InvalidFen { game: usize, error: FenError },
+	IllegalMove { game: usize, ply: usize, san: String, error: SanError },
+}
+
+impl fmt::Display for PgnError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			PgnError::Io(error) => write!(f, "{}", error),
+			PgnError::Syntax { game, line, message } => write!(f, "game {}, line {}: {}", game, line, message),
+			PgnError::InvalidFen { game, error } => write!(f, "game {}: bad FEN tag: {}", game, error),
+			PgnError::IllegalMove { game, ply, san, error } => write!(f, "game {}, ply {}: cannot play '{}': {}", game, ply, san, error),
+		}
+	}
+}
+
+impl std::error::Error for PgnError {}
+
+impl From<io::Error> for PgnError {
+	fn from(error: io::Error) -> PgnError {
+		PgnError::Io(error)
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+	Tag(String, String),
+	Comment,
+	Nag,
+	VariationStart,
+	VariationEnd,
+	San(String),
+	Result(String),
+	/// Text that couldn't be read, with a message saying why. Tokenizing carries on after it.
+	Error(String),
+}
+
+/// Reads games one at a time from PGN text. Comments and NAGs are parsed and skipped. Every move
+/// is checked by playing it, those in recursive variations on a copy of the position where the
+/// variation branches off, so an illegal move is reported with the game and ply number where it
+/// occurred; only the main line is kept in the record. After an error the reader moves on to the
+/// next game. A tag pair that follows movetext, or repeats a tag of the current game, starts a new
+/// game, so a game with tags but no movetext doesn't run into the next one.
+pub struct PgnReader<R: BufRead> {
+	input: R,
+	line_number: usize,
+	game_number: usize,
+	tokens: VecDeque<(Token, usize)>,
+	in_comment: bool,
+	finished: bool,
+}
+
+impl PgnReader<BufReader<File>> {
+	pub fn open<P: AsRef<Path>>(path: P) -> io::Result<PgnReader<BufReader<File>>> {
+		Ok(PgnReader::new(BufReader::new(File::open(path)?)))
+	}
+}
+
+impl<R: BufRead> PgnReader<R> {
+	pub fn new(input: R) -> PgnReader<R> {
+		PgnReader {
+			input,
+			line_number: 0,
+			game_number: 0,
+			tokens: VecDeque::new(),
+			in_comment: false,
+			finished: false,
+		}
+	}
+
+	/// The next token, reading more lines as needed. `None` means the input is exhausted.
+	fn next_token(&mut self) -> Result<Option<(Token, usize)>, PgnError> {
+		while self.tokens.is_empty() {
+			if self.finished {
+				return Ok(None);
+			}
+			let mut line = String::new();
+			if self.input.read_line(&mut line)? == 0 {
+				self.finished = true;
+				return Ok(None);
+			}
+			self.line_number += 1;
+			self.tokenize_line(&line);
+		}
+		Ok(self.tokens.pop_front())
+	}
+
+	/// Splits a line into tokens. Anything unreadable becomes an error token, and the rest of the
+	/// line is still tokenized so that a result after it ends the game where it should.
+	fn tokenize_line(&mut self, line: &str) {
+		if line.starts_with('%') && !self.in_comment {
+			return;
+		}
+		let line_number = self.line_number;
+		let chars: Vec<char> = line.chars().collect();
+		let mut i = 0;
+		while i < chars.len() {
+			if self.in_comment {
+				match chars[i..].iter().position(|c| *c == '}') {
+					Some(end) => {
+						self.in_comment = false;
+						self.tokens.push_back((Token::Comment, line_number));
+						i += end + 1;
+					},
+					None => return,
+				}
+				continue;
+			}
+			let c = chars[i];
+			match c {
+				_ if c.is_whitespace() => i += 1,
+				'{' => {
+					self.in_comment = true;
+					i += 1;
+				},
+				';' => {
+					self.tokens.push_back((Token::Comment, line_number));
+					return;
+				},
+				'(' => {
+					self.tokens.push_back((Token::VariationStart, line_number));
+					i += 1;
+				},
+				')' => {
+					self.tokens.push_back((Token::VariationEnd, line_number));
+					i += 1;
+				},
+				'[' => match read_tag(&chars, i) {
+					Ok((name, value, end)) => {
+						self.tokens.push_back((Token::Tag(name, value), line_number));
+						i = end;
+					},
+					Err(message) => {
+						self.tokens.push_back((Token::Error(message), line_number));
+						// Skip the broken tag up to its closing bracket, if it has one.
+						i = chars[i..].iter().position(|c| *c == ']').map_or(chars.len(), |end| i + end + 1);
+					},
+				},
+				'$' => {
+					let digits = chars[i + 1..].iter().take_while(|c| c.is_ascii_digit()).count();
+					let token = if digits == 0 { Token::Error(String::from("'$' without a NAG number")) } else { Token::Nag };
+					self.tokens.push_back((token, line_number));
+					i += 1 + digits;
+				},
+				_ => {
+					let length = chars[i..].iter().take_while(|c| !c.is_whitespace() && !"{}()[];$".contains(**c)).count();
+					if length == 0 {
+						self.tokens.push_back((Token::Error(format!("unexpected '{}'", c)), line_number));
+						i += 1;
+						continue;
+					}
+					let symbol: String = chars[i..i + length].iter().collect();
+					i += length;
+					if let Some(token) = classify_symbol(&symbol) {
+						self.tokens.push_back((token, line_number));
+					}
+				},
+			}
+		}
+	}
+
+
+	/// Assembles the next game from the token stream.
+	fn read_game(&mut self) -> Option<Result<GameRecord, PgnError>> {
+		let mut tags = Vec::new();
+		let mut record: Option<GameRecord> = None;
+		let mut error: Option<PgnError> = None;
+		// The position in each open variation, innermost last, and how many moves it has so far.
+		let mut variations: Vec<(GameState, usize)> = Vec::new();
+		let mut in_movetext = false;
+		let mut started = false;
+
+		loop {
+			let (token, line) = match self.next_token() {
+				Ok(Some(next)) => next,
+				Ok(None) => break,
+				Err(io_error) => return Some(Err(io_error)),
+			};
+			if !started {
+				self.game_number += 1;
+				started = true;
+			}
+			if matches!(token, Token::Nag | Token::VariationStart | Token::VariationEnd | Token::San(_) | Token::Result(_)) {
+				in_movetext = true;
+			}
+			match token {
+				Token::Tag(name, value) => {
+					if in_movetext || tags.iter().any(|(tag, _)| *tag == name) {
+						self.tokens.push_front((Token::Tag(name, value), line));
+						break;
+					}
+					tags.push((name, value));
+				},
+				Token::Comment | Token::Nag => (),
+				Token::Error(message) => {
+					error.get_or_insert(PgnError::Syntax { game: self.game_number, line, message });
+				},
+				Token::VariationStart => {
+					// A variation replaces the last move of the line it branches from.
+					let branch = match variations.last() {
+						Some((position, moves)) => Some(position).filter(|_| *moves > 0),
+						None => record.as_ref().filter(|record| !record.moves().is_empty()).map(GameRecord::game),
+					};
+					match branch {
+						Some(position) => {
+							let mut position = position.clone();
+							position.undo();
+							variations.push((position, 0));
+						},
+						None => {
+							error.get_or_insert(PgnError::Syntax { game: self.game_number, line, message: String::from("variation does not follow a move") });
+							variations.push((GameState::new(), 0));
+						},
+					}
+				},
+				Token::VariationEnd => {
+					if variations.pop().is_none() {
+						error.get_or_insert(PgnError::Syntax { game: self.game_number, line, message: String::from("')' without a matching '('") });
+					}
+				},
+				Token::San(san) => {
+					let record = match &mut record {
+						Some(record) => record,
+						None => record.insert(match start_record(&tags, self.game_number) {
+							Ok(record) => record,
+							Err(fen_error) => {
+								error.get_or_insert(fen_error);
+								GameRecord::new()
+							},
+						}),
+					};
+					if error.is_some() {
+						continue;
+					}
+					match variations.last_mut() {
+						None => match record.game().parse_san(&san) {
+							Ok(m) => {
+								record.make_move(m);
+							},
+							Err(san_error) => {
+								error = Some(PgnError::IllegalMove { game: self.game_number, ply: record.moves().len() + 1, san, error: san_error });
+							},
+						},
+						Some((position, moves)) => match position.parse_san(&san) {
+							Ok(m) => {
+								position.make_move(m);
+								*moves += 1;
+							},
+							Err(san_error) => {
+								let ply = ply_index(position) + 1 - ply_index(record.start());
+								error = Some(PgnError::IllegalMove { game: self.game_number, ply, san, error: san_error });
+							},
+						},
+					}
+				},
+				Token::Result(result) => {
+					if variations.is_empty() {
+						let record = record.get_or_insert_with(|| start_record(&tags, self.game_number).unwrap_or_else(|fen_error| {
+							error.get_or_insert(fen_error);
+							GameRecord::new()
+						}));
+						record.set_tag("Result", &result);
+						break;
+					}
+				},
+			}
+		}
+
+		if !started {
+			return None;
+		}
+		if let Some(error) = error {
+			return Some(Err(error));
+		}
+		let record = match record {
+			Some(record) => record,
+			None => match start_record(&tags, self.game_number) {
+				Ok(record) => record,
+				Err(fen_error) => return Some(Err(fen_error)),
+			},
+		};
+		Some(Ok(record))
+	}
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+	type Item = Result<GameRecord, PgnError>;
+
+	fn next(&mut self) -> Option<Result<GameRecord, PgnError>> {
+		self.read_game()
+	}
+}
+
+/// How many plies were played before `position`, counting from the start of the game.
+fn ply_index(position: &GameState) -> usize {
+	let black_to_move = position.side_to_move == Side::Black;
+	(position.fullmove_number as usize - 1) * 2 + black_to_move as usize
+}
+
+/// Reads `[Name "value"]` starting at `start`, returning the name, the unescaped value and the
+/// index just past the closing bracket.
+fn read_tag(chars: &[char], start: usize) -> Result<(String, String, usize), String> {
+	let mut i = start + 1;
+	while i < chars.len() && chars[i].is_whitespace() {
+		i += 1;
+	}
+	let name_start = i;
+	while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+		i += 1;
+	}
+	let name: String = chars[name_start..i].iter().collect();
+	while i < chars.len() && chars[i].is_whitespace() {
+		i += 1;
+	}
+	if name.is_empty() || i >= chars.len() || chars[i] != '"' {
+		return Err(String::from("malformed tag pair"));
+	}
+	i += 1;
+	let mut value = String::new();
+	loop {
+		match chars.get(i) {
+			None => return Err(format!("unterminated value for tag {}", name)),
+			Some('"') => break,
+			Some('\\') if i + 1 < chars.len() => {
+				value.push(chars[i + 1]);
+				i += 2;
+			},
+			Some(c) => {
+				value.push(*c);
+				i += 1;
+			},
+		}
+	}
+	i += 1;
+	while i < chars.len() && chars[i].is_whitespace() {
+		i += 1;
+	}
+	if chars.get(i) != Some(&']') {
+		return Err(format!("tag {} is missing its closing ']'", name));
+	}
+	Ok((name, value, i + 1))
+}
+
+/// Creates the record for a game from its tag section, starting from the FEN tag if there is one.
+fn start_record(tags: &[(String, String)], game_number: usize) -> Result<GameRecord, PgnError> {
+	let fen = tags.iter().find(|(name, _)| name == "FEN").map(|(_, value)| value);
+	let mut record = match fen {
+		Some(fen) => GameRecord::from_position(GameState::from_fen(fen).map_err(|error| PgnError::InvalidFen { game: game_number, error })?),
+		None => GameRecord::new(),
+	};
+	for (name, value) in tags {
+		record.set_tag(name, value);
+	}
+	Ok(record)
+}
+
+/// Sorts a bare symbol from the movetext into a result, a move or nothing at all (move numbers
+/// and "!?"-style annotations). A move number may be glued to its move, as in "1.e4".
+fn classify_symbol(symbol: &str) -> Option<Token> {
+	if matches!(symbol, "1-0" | "0-1" | "1/2-1/2" | "*") {
+		return Some(Token::Result(String::from(symbol)));
+	}
+	let without_number = match symbol.find(|c: char| !c.is_ascii_digit()) {
+		Some(index) if index > 0 && symbol[index..].starts_with('.') => symbol[index..].trim_start_matches('.'),
+		_ => symbol,
+	};
+	if without_number.is_empty() || without_number.chars().all(|c| c == '!' || c == '?') {
+		return None;
+	}
+	Some(Token::San(String::from(without_number)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ai;
+
+	fn play(record: &mut GameRecord, moves: &[&str]) {
+		for san in moves {
+			let m = record.game().parse_san(san).unwrap();
+			record.make_move(m);
+		}
+	}
+
+	#[test]
+	fn test_export() {
+		let mut record = GameRecord::new();
+		record.set_tag("Event", "Club \"Blitz\" Night");
+		record.set_tag("White", "Anderssen, Adolf");
+		record.set_tag("Black", "Kieseritzky, Lionel");
+		record.set_tag("ECO", "C33");
+		play(&mut record, &["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"]);
+		assert_eq!(record.to_pgn(), "\
+[Event \"Club \\\"Blitz\\\" Night\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"Anderssen, Adolf\"]
+[Black \"Kieseritzky, Lionel\"]
+[Result \"1-0\"]
+[ECO \"C33\"]
+
+1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0
+");
+	}
+
+	#[test]
+	fn test_export_unfinished_and_resigned() {
+		let mut record = GameRecord::new();
+		play(&mut record, &["d4"]);
+		assert!(record.to_pgn().ends_with("\n\n1. d4 *\n"));
+		record.resign(Side::Black);
+		assert!(record.to_pgn().contains("[Result \"1-0\"]"));
+		assert!(record.to_pgn().ends_with("\n\n1. d4 1-0\n"));
+	}
+
+	#[test]
+	fn test_export_from_position() {
+		let start = GameState::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 40").unwrap();
+		let mut record = GameRecord::from_position(start);
+		play(&mut record, &["Kd7", "e4", "Kc6"]);
+		let pgn = record.to_pgn();
+		assert!(pgn.contains("[Result \"*\"]\n[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 40\"]\n\n"));
+		assert!(pgn.ends_with("\n\n40... Kd7 41. e4 Kc6 *\n"));
+	}
+
+	#[test]
+	fn test_export_wraps_lines() {
+		let mut record = GameRecord::new();
+		while record.game().outcome().is_none() && record.moves().len() < 200 {
+			let m = ai::next_move(record.game()).unwrap();
+			record.make_move(m);
+		}
+		let pgn = record.to_pgn();
+		let movetext = pgn.split("\n\n").nth(1).unwrap();
+		assert!(movetext.lines().count() > 1);
+		for line in movetext.lines() {
+			assert!(line.len() <= MAX_LINE_LENGTH);
+			assert!(!line.starts_with(' ') && !line.ends_with(' '));
+		}
+		assert!(movetext.trim_end().ends_with(&record.result()));
+	}
+
+	fn read_all(pgn: &str) -> Vec<Result<GameRecord, PgnError>> {
+		PgnReader::new(pgn.as_bytes()).collect()
+	}
+
+	#[test]
+	fn test_import_annotated_games() {
+		let pgn = "\
+% exported by a program that writes escape lines
+[Event \"Casual \\\"Game\\\"\"]
+[Site \"London\"]
+[White \"Anderssen\"]
+[Black \"Kieseritzky\"]
+[Result \"1-0\"]
+
+1.e4 e5 2. f4 exf4 3. Bc4 Qh4+ $1 4. Kf1 b5?! {A romantic
+offer, spanning lines} 5. Bxb5 Nf6 6. Nf3 Qh6 (6... Qh5 (6... Qg4? 7. h3) 7. d3) 7. d3
+; a rest-of-line comment with ) and { inside
+7... Nh5 8. Nh4 Qg5 9. Nf5 c6 10. g4 Nf6 11. Rg1! cxb5 12. h4 Qg6 13. h5 Qg5 14. Qf3 Ng8
+15. Bxf4 Qf6 16. Nc3 Bc5 17. Nd5 Qxb2 18. Bd6 Bxg1 19. e5 Qxa1+ 20. Ke2 Na6 21. Nxg7+ Kd8
+22. Qf6+ Nxf6 23. Be7# 1-0
+
+[Event \"Second\"]
+[SetUp \"1\"]
+[FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]
+
+1. e4 Kd7 2. e5 $14 Ke6 *
+";
+		let games = read_all(pgn);
+		assert_eq!(games.len(), 2);
+		let immortal = games[0].as_ref().unwrap();
+		assert_eq!(immortal.tag("Event"), Some("Casual \"Game\""));
+		assert_eq!(immortal.tag("White"), Some("Anderssen"));
+		assert_eq!(immortal.moves().len(), 45);
+		assert!(immortal.game().is_in_checkmate(Side::Black));
+		assert_eq!(immortal.result(), "1-0");
+
+		let second = games[1].as_ref().unwrap();
+		assert_eq!(second.tag("Event"), Some("Second"));
+		assert_eq!(second.moves().len(), 4);
+		assert_eq!(second.game().to_fen(), "8/8/4k3/4P3/8/8/8/4K3 w - - 1 3");
+		assert_eq!(second.result(), "*");
+	}
+
+	#[test]
+	fn test_import_reports_illegal_moves() {
+		let pgn = "\
+[Event \"One\"]
+
+1. e4 e5 2. Ke3 Nc6 1/2-1/2
+
+[Event \"Two\"]
+
+1. d4 d5 2. c4 c5 3. Qa4+ Bd7 4. Qxd7+ Qxd7 5. Bxg7 0-1
+
+[Event \"Three\"]
+
+1. Nf3 *
+";
+		let games = read_all(pgn);
+		assert_eq!(games.len(), 3);
+		match &games[0] {
+			Err(PgnError::IllegalMove { game: 1, ply: 3, san, error: SanError::NoMatchingMove(_) }) => assert_eq!(san, "Ke3"),
+			other => panic!("unexpected {:?}", other.as_ref().map(|record| record.to_pgn())),
+		}
+		match &games[1] {
+			Err(error @ PgnError::IllegalMove { game: 2, ply: 9, .. }) => assert_eq!(error.to_string(), "game 2, ply 9: cannot play 'Bxg7': 'Bxg7' does not match any legal move"),
+			other => panic!("unexpected {:?}", other.as_ref().map(|record| record.to_pgn())),
+		}
+		assert_eq!(games[2].as_ref().unwrap().moves().len(), 1);
+
+		// Moves in variations are played from where the variation branches off.
+		let games = read_all("1. e4 e5 (1... Ke7 2. Ke2) 2. Nf3 *\n1. e4 e5 2. Nf3 (2. Nc3 (2. Nf6)) Nc6 *\n( 1. e4 ) 1. d4 *\n");
+		assert_eq!(games.len(), 3);
+		match &games[0] {
+			Err(PgnError::IllegalMove { game: 1, ply: 2, san, .. }) => assert_eq!(san, "Ke7"),
+			other => panic!("unexpected {:?}", other.as_ref().map(|record| record.to_pgn())),
+		}
+		match &games[1] {
+			Err(PgnError::IllegalMove { game: 2, ply: 3, san, .. }) => assert_eq!(san, "Nf6"),
+			other => panic!("unexpected {:?}", other.as_ref().map(|record| record.to_pgn())),
+		}
+		assert!(matches!(&games[2], Err(PgnError::Syntax { game: 3, line: 3, message }) if message == "variation does not follow a move"));
+	}
+
+	#[test]
+	fn test_import_errors() {
+		let games = read_all("[FEN \"8/8/8 w - - 0 1\"]\n\n1. e4 *\n\n1. d4 *\n");
+		assert!(matches!(games[0], Err(PgnError::InvalidFen { game: 1, error: FenError::WrongRankCount(3) })));
+		assert_eq!(games[1].as_ref().unwrap().moves().len(), 1);
+
+		let games = read_all("[Event \"Broken\n\n1. e4 *\n");
+		assert!(matches!(games[0], Err(PgnError::Syntax { game: 1, line: 1, .. })));
+
+		let games = read_all("1. e4 ) e5 *\n1. c4 *");
+		assert!(matches!(games[0], Err(PgnError::Syntax { game: 1, line: 1, .. })));
+		assert_eq!(games[1].as_ref().unwrap().moves().len(), 1);
+
+		// The rest of the line is still read, so the result ends the first game.
+		let games = read_all("1. e4 $ e5 1-0\n1. d4 *\n");
+		assert_eq!(games.len(), 2);
+		match &games[0] {
+			Err(error @ PgnError::Syntax { game: 1, line: 1, .. }) => assert_eq!(error.to_string(), "game 1, line 1: '$' without a NAG number"),
+			other => panic!("unexpected {:?}", other.as_ref().map(|record| record.to_pgn())),
+		}
+		assert_eq!(games[1].as_ref().unwrap().moves().len(), 1);
+		let games = read_all("1. e4 e5 } 2. Nf3 0-1\n[Event \"Next\"]\n1. c4 *\n");
+		assert_eq!(games.len(), 2);
+		assert!(matches!(games[0], Err(PgnError::Syntax { game: 1, line: 1, .. })));
+		assert_eq!(games[1].as_ref().unwrap().tag("Event"), Some("Next"));
+
+		// A game with tags and nothing else still ends where the next one's tags begin.
+		let games = read_all("[Event \"Empty\"]\n\n[Event \"Real\"]\n\n1. e4 *\n");
+		assert_eq!(games.len(), 2);
+		assert_eq!(games[0].as_ref().unwrap().tag("Event"), Some("Empty"));
+		assert!(games[0].as_ref().unwrap().moves().is_empty());
+		assert_eq!(games[1].as_ref().unwrap().tag("Event"), Some("Real"));
+		assert_eq!(games[1].as_ref().unwrap().moves().len(), 1);
+
+				assert!(read_all("").is_empty());
+		assert!(read_all("\n\n  \n").is_empty());
+	}
+
+	#[test]
+	fn test_round_trip() {
+		let mut record = GameRecord::new();
+		record.set_tag("Event", "Round trip");
+		while record.game().outcome().is_none() && record.moves().len() < 150 {
+			let m = ai::next_move(record.game()).unwrap();
+			record.make_move(m);
+		}
+		let games = read_all(&(record.to_pgn() + "\n" + &record.to_pgn()));
+		assert_eq!(games.len(), 2);
+		for game in games {
+			let game = game.unwrap();
+			assert_eq!(game.moves(), record.moves());
+			assert_eq!(game.to_pgn(), record.to_pgn());
+		}
+	}
+}
@@ -0,0 +1,181 @@
+use std::mem::size_of;
+use crate::ai::MATE_THRESHOLD;
+use crate::chess::*;
+
+/// How a stored score relates to the true score of the position.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Bound {
+	#[default]
+	Exact,
+	/// The search failed high, so the true score is at least this.
+	Lower,
+	/// The search failed low, so the true score is at most this.
+	Upper,
+}
+
+/// What the search learned about one position.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Entry {
+	key: u64,
+	pub best_move: Option<Move>,
+	pub score: i32,
+	pub depth: u8,
+	pub bound: Bound,
+	/// The search that stored the entry. Entries from earlier searches are replaced first.
+	age: u8,
+}
+
+/// A fixed-size hash table of searched positions, keyed by `GameState::hash`. Each position has one
+/// slot, and a new entry replaces the one there unless that entry is from the current search and
+/// was searched deeper, so the most useful results survive while stale ones make way.
+pub struct TranspositionTable {
+	entries: Vec<Entry>,
+	age: u8,
+}
+
+impl TranspositionTable {
+	/// A table taking up at most `megabytes` of memory, with a power-of-two number of entries and
+	/// at least one.
+	pub fn new(megabytes: usize) -> TranspositionTable {
+		let capacity = (megabytes * 1024 * 1024 / size_of::<Entry>()).max(1);
+		let capacity = 1 << capacity.ilog2();
+		TranspositionTable { entries: vec![Entry::default(); capacity], age: 0 }
+	}
+
+	pub fn capacity(&self) -> usize {
+		self.entries.len()
+	}
+
+	/// Forgets everything, for example before a new game.
+	pub fn clear(&mut self) {
+		self.entries.fill(Entry::default());
+		self.age = 0;
+	}
+
+	/// Marks the start of a search, which makes everything stored so far replaceable.
+	pub fn new_search(&mut self) {
+		self.age = self.age.wrapping_add(1);
+	}
+
+	/// The entry for the position with this hash, with a mate score counted from the position
+	/// `ply` plies into the search.
+	pub fn probe(&self, hash: u64, ply: i32) -> Option<Entry> {
+		let entry = self.entries[self.index(hash)];
+		if entry.key != hash || entry.depth == 0 {
+			return None;
+		}
+		Some(Entry { score: from_table_score(entry.score, ply), ..entry })
+	}
+
+	/// Stores a search result for the position with this hash, `ply` plies into the search. A
+	/// result without a best move keeps the move already stored for the same position.
+	pub fn store(&mut self, hash: u64, depth: u32, score: i32, bound: Bound, best_move: Option<Move>, ply: i32) {
+		let depth = depth.clamp(1, u8::MAX as u32) as u8;
+		let index = self.index(hash);
+		let old = self.entries[index];
+		let same_position = old.key == hash && old.depth > 0;
+		if !same_position && old.age == self.age && old.depth > depth {
+			return;
+		}
+		self.entries[index] = Entry {
+			key: hash,
+			best_move: best_move.or(old.best_move.filter(|_| same_position)),
+			score: to_table_score(score, ply),
+			depth,
+			bound,
+			age: self.age,
+		};
+	}
+
+	/// How full the table is, in permille, judged from the first thousand slots.
+	pub fn hashfull(&self) -> usize {
+		let sample = &self.entries[..self.entries.len().min(1000)];
+		sample.iter().filter(|entry| entry.depth > 0 && entry.age == self.age).count() * 1000 / sample.len()
+	}
+
+	fn index(&self, hash: u64) -> usize {
+		hash as usize & (self.entries.len() - 1)
+	}
+}
+
+/// Mate scores count plies from the root, but the same position can be reached at any ply, so the
+/// table counts them from the position itself instead.
+fn to_table_score(score: i32, ply: i32) -> i32 {
+	if score >= MATE_THRESHOLD {
+		score + ply
+	} else if score <= -MATE_THRESHOLD {
+		score - ply
+	} else {
+		score
+	}
+}
+
+fn from_table_score(score: i32, ply: i32) -> i32 {
+	if score >= MATE_THRESHOLD {
+		score - ply
+	} else if score <= -MATE_THRESHOLD {
+		score + ply
+	} else {
+		score
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ai::MATE;
+
+	#[test]
+	fn test_store_and_probe() {
+		let mut table = TranspositionTable::new(1);
+		assert!(table.capacity().is_power_of_two());
+		assert!(table.capacity() * size_of::<Entry>() <= 1024 * 1024);
+		let m = Move::new((1, 4), (3, 4));
+		assert_eq!(table.probe(42, 0), None);
+
+		table.store(42, 3, 25, Bound::Lower, Some(m), 2);
+		let entry = table.probe(42, 5).unwrap();
+		assert_eq!((entry.best_move, entry.score, entry.depth, entry.bound), (Some(m), 25, 3, Bound::Lower));
+		assert_eq!(table.probe(42 + table.capacity() as u64, 0), None);
+
+		// Failing low finds no best move, so the old one is kept.
+		table.store(42, 4, -10, Bound::Upper, None, 2);
+		assert_eq!(table.probe(42, 0).unwrap().best_move, Some(m));
+
+		table.clear();
+		assert_eq!(table.probe(42, 0), None);
+		assert_eq!(table.hashfull(), 0);
+	}
+
+	#[test]
+	fn test_mate_scores() {
+		let mut table = TranspositionTable::new(1);
+		// Mate in three plies from a position four plies into the search.
+		table.store(7, 5, MATE - 7, Bound::Exact, None, 4);
+		assert_eq!(table.probe(7, 4).unwrap().score, MATE - 7);
+		assert_eq!(table.probe(7, 2).unwrap().score, MATE - 5);
+		table.store(8, 5, -(MATE - 6), Bound::Exact, None, 6);
+		assert_eq!(table.probe(8, 1).unwrap().score, -(MATE - 1));
+		table.store(9, 5, 150, Bound::Exact, None, 6);
+		assert_eq!(table.probe(9, 1).unwrap().score, 150);
+	}
+
+	#[test]
+	fn test_replacement() {
+		let mut table = TranspositionTable::new(1);
+		let collision = 5 + table.capacity() as u64;
+		table.store(5, 6, 10, Bound::Exact, None, 0);
+		// A shallower result for another position doesn't replace a deeper one from this search...
+		table.store(collision, 2, 20, Bound::Exact, None, 0);
+		assert_eq!(table.probe(5, 0).unwrap().depth, 6);
+		assert_eq!(table.probe(collision, 0), None);
+		// ...but does once the search is over.
+		table.new_search();
+		table.store(collision, 2, 20, Bound::Exact, None, 0);
+		assert_eq!(table.probe(5, 0), None);
+		assert_eq!(table.probe(collision, 0).unwrap().score, 20);
+		// The same position is always updated.
+		table.store(collision, 1, 30, Bound::Upper, None, 0);
+		assert_eq!(table.probe(collision, 0).unwrap().score, 30);
+	}
+}
@@ -0,0 +1,46 @@
+use crate::chess::*;
+
+/// The random numbers behind `GameState::hash`: 768 piece keys (12 pieces on 64 squares), 4
+/// castling rights, 8 en passant files and the side to move. They are generated with SplitMix64 at
+/// compile time so that hashes are the same on every run, but they are this program's own and won't
+/// match the hashes of opening books or other engines.
+const KEYS: [u64; 781] = {
+	let mut keys = [0; 781];
+	let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+	let mut i = 0;
+	while i < keys.len() {
+		state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+		let mut z = state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+		keys[i] = z ^ (z >> 31);
+		i += 1;
+	}
+	keys
+};
+
+const CASTLING_OFFSET: usize = 768;
+const EN_PASSANT_OFFSET: usize = 772;
+const SIDE_TO_MOVE_OFFSET: usize = 780;
+
+pub fn piece_key(piece: Piece, square: (i8, i8)) -> u64 {
+	let piece_index = piece.piece_type() as usize * 2 + match piece.side() {
+		Side::White => 1,
+		Side::Black => 0,
+	};
+	KEYS[piece_index * 64 + square.0 as usize * 8 + square.1 as usize]
+}
+
+/// The key for one castling right, numbered in FEN order: K, Q, k, q.
+pub fn castling_key(right: usize) -> u64 {
+	KEYS[CASTLING_OFFSET + right]
+}
+
+pub fn en_passant_key(file: i8) -> u64 {
+	KEYS[EN_PASSANT_OFFSET + file as usize]
+}
+
+/// Included in the hash when it is White to move.
+pub fn side_to_move_key() -> u64 {
+	KEYS[SIDE_TO_MOVE_OFFSET]
+}